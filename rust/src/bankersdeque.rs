@@ -0,0 +1,210 @@
+// Section 8.4.2
+//
+// Banker's deque.
+// Amortized O(1) operations on both ends even if old versions are used persistently
+
+use std::fmt::Debug;
+use stream::Stream;
+use ptr::Shareable;
+use traits;
+
+// Note:
+// Invariants: |f| <= c|r| + 1 and |r| <= c|f| + 1
+// When one side gets too long, the deque is rebalanced so that both sides have a half of elements.
+// Elements moved to the other side are appended to its end with suspended `reverse`, so they are
+// not forced until the side is consumed.
+// With larger c, rebalancing happens less often but each rebalance moves more elements.
+#[derive(Clone, Debug)]
+pub struct BankersDeque<'a, T: 'a + Clone + Debug + Shareable> {
+    c: usize,
+    lenf: usize,
+    f: Stream<'a, T>,
+    lenr: usize,
+    r: Stream<'a, T>,
+}
+
+impl<'a, T> BankersDeque<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    // The book suggests 2 or 3 for c
+    pub fn empty() -> Self {
+        BankersDeque::with_balance(3)
+    }
+
+    // Returns None when c is not greater than 1
+    pub fn try_with_balance(c: usize) -> Option<Self> {
+        if c <= 1 {
+            return None;
+        }
+        Some(BankersDeque{c, lenf: 0, f: Stream::empty(), lenr: 0, r: Stream::empty()})
+    }
+
+    pub fn with_balance(c: usize) -> Self {
+        BankersDeque::try_with_balance(c).expect("balance constant must be greater than 1!")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lenf + self.lenr == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.lenf + self.lenr
+    }
+
+    fn check(&self, lenf: usize, f: Stream<'a, T>, lenr: usize, r: Stream<'a, T>) -> Self {
+        let c = self.c;
+        let len = lenf + lenr;
+        if lenf > c * lenr + 1 {
+            let i = len / 2;
+            let r = r.concat(&f.drop(i).reverse());
+            BankersDeque{c, lenf: i, f: f.take(i), lenr: len - i, r}
+        } else if lenr > c * lenf + 1 {
+            let j = len / 2;
+            let f = f.concat(&r.drop(j).reverse());
+            BankersDeque{c, lenf: len - j, f, lenr: j, r: r.take(j)}
+        } else {
+            BankersDeque{c, lenf, f, lenr, r}
+        }
+    }
+
+    pub fn enq_front(&self, x: T) -> Self {
+        self.check(self.lenf + 1, self.f.cons(x), self.lenr, self.r.clone())
+    }
+
+    pub fn enq_back(&self, x: T) -> Self {
+        self.check(self.lenf, self.f.clone(), self.lenr + 1, self.r.cons(x))
+    }
+
+    pub fn try_deq_front(&self) -> Option<Self> {
+        match self.f.try_tail() {
+            Some(f) => Some(self.check(self.lenf - 1, f, self.lenr, self.r.clone())),
+            // Only one element is in r due to invariant
+            None if self.lenr > 0 => Some(BankersDeque::with_balance(self.c)),
+            None => None,
+        }
+    }
+
+    pub fn try_deq_back(&self) -> Option<Self> {
+        match self.r.try_tail() {
+            Some(r) => Some(self.check(self.lenf, self.f.clone(), self.lenr - 1, r)),
+            // Only one element is in f due to invariant
+            None if self.lenf > 0 => Some(BankersDeque::with_balance(self.c)),
+            None => None,
+        }
+    }
+
+    pub fn deq_front(&self) -> Self {
+        self.try_deq_front().expect("Deque is empty!")
+    }
+
+    pub fn deq_back(&self) -> Self {
+        self.try_deq_back().expect("Deque is empty!")
+    }
+
+    pub fn try_front(&self) -> Option<&T> {
+        self.f.try_head().or_else(|| self.r.try_head())
+    }
+
+    pub fn try_back(&self) -> Option<&T> {
+        self.r.try_head().or_else(|| self.f.try_head())
+    }
+
+    pub fn front(&self) -> &T {
+        self.try_front().expect("Deque is empty!")
+    }
+
+    pub fn back(&self) -> &T {
+        self.try_back().expect("Deque is empty!")
+    }
+
+    pub fn pop_front(&self) -> Option<(&T, Self)> {
+        self.try_front().and_then(|x| self.try_deq_front().map(|d| (x, d)))
+    }
+
+    pub fn pop_back(&self) -> Option<(&T, Self)> {
+        self.try_back().and_then(|x| self.try_deq_back().map(|d| (x, d)))
+    }
+}
+
+impl<'a, T> traits::Deque<T> for BankersDeque<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn empty() -> Self {
+        BankersDeque::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn enq_front(&self, x: T) -> Self {
+        self.enq_front(x)
+    }
+
+    fn enq_back(&self, x: T) -> Self {
+        self.enq_back(x)
+    }
+
+    fn try_front(&self) -> Option<&T> {
+        self.try_front()
+    }
+
+    fn try_back(&self) -> Option<&T> {
+        self.try_back()
+    }
+
+    fn try_deq_front(&self) -> Option<Self> {
+        self.try_deq_front()
+    }
+
+    fn try_deq_back(&self) -> Option<Self> {
+        self.try_deq_back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enq_deq() {
+        let d = BankersDeque::<i32>::empty();
+        assert!(d.is_empty());
+        assert!(d.try_front().is_none());
+        assert!(d.try_deq_back().is_none());
+        let d = d.enq_back(2).enq_front(1).enq_back(3);
+        assert_eq!(d.len(), 3);
+        assert_eq!(d.front(), &1);
+        assert_eq!(d.back(), &3);
+        let d = d.deq_front().deq_front();
+        assert_eq!(d.front(), &3);
+        assert_eq!(d.back(), &3);
+        assert!(d.deq_back().is_empty());
+    }
+
+    #[test]
+    fn test_one_side() {
+        // Enqueuing to and dequeuing from only one side keeps rebalancing
+        let mut d = BankersDeque::empty();
+        for i in 0..100 {
+            d = d.enq_back(i);
+        }
+        for i in 0..100 {
+            assert_eq!(d.front(), &i);
+            d = d.deq_front();
+        }
+        assert!(d.is_empty());
+        for i in 0..100 {
+            d = d.enq_front(i);
+        }
+        for i in 0..100 {
+            assert_eq!(d.front(), &(99 - i));
+            d = d.deq_front();
+        }
+        assert!(d.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_balance() {
+        assert!(BankersDeque::<i32>::try_with_balance(1).is_none());
+        assert!(BankersDeque::<i32>::try_with_balance(2).is_some());
+    }
+}
@@ -0,0 +1,188 @@
+// Section 6.3.2
+//
+// Banker's queue.
+// Amortized O(1) operations (enqueue/dequeue) even if old versions are used persistently
+
+use std::fmt::Debug;
+use stream::Stream;
+use ptr::Shareable;
+use traits;
+
+// Note:
+// Invariants: |r| <= |f|
+// When r gets longer than f, f is replaced with `f ++ reverse r`. The rotation is suspended and it
+// is forced only after all elements of the old f are dequeued. Since the suspension is shared by all
+// versions, dequeuing from the same old version repeatedly does not repeat the reversal.
+#[derive(Clone, Debug)]
+pub struct BankersQueue<'a, T: 'a + Clone + Debug + Shareable> {
+    lenf: usize,
+    f: Stream<'a, T>,
+    lenr: usize,
+    r: Stream<'a, T>,
+}
+
+impl<'a, T> BankersQueue<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    pub fn empty() -> Self {
+        BankersQueue{lenf: 0, f: Stream::empty(), lenr: 0, r: Stream::empty()}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lenf == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.lenf + self.lenr
+    }
+
+    fn check(lenf: usize, f: Stream<'a, T>, lenr: usize, r: Stream<'a, T>) -> Self {
+        if lenr <= lenf {
+            BankersQueue{lenf, f, lenr, r}
+        } else {
+            BankersQueue {
+                lenf: lenf + lenr,
+                f: f.concat(&r.reverse()),
+                lenr: 0,
+                r: Stream::empty(),
+            }
+        }
+    }
+
+    pub fn enq(&self, x: T) -> Self {
+        BankersQueue::check(self.lenf, self.f.clone(), self.lenr + 1, self.r.cons(x))
+    }
+
+    pub fn pop(&self) -> Option<(&T, Self)> {
+        self.f.uncons().map(|(x, f)| (x, BankersQueue::check(self.lenf - 1, f, self.lenr, self.r.clone())))
+    }
+
+    pub fn try_top(&self) -> Option<&T> {
+        self.f.try_head()
+    }
+
+    pub fn try_deq(&self) -> Option<Self> {
+        self.pop().map(|(_, q)| q)
+    }
+
+    pub fn top(&self) -> &T {
+        self.try_top().expect("Queue is empty")
+    }
+
+    pub fn deq(&self) -> Self {
+        self.try_deq().expect("Queue is empty")
+    }
+
+    // Same as `Stream::into_unique_elems` for both streams. The order of elements is not kept.
+    pub fn into_unique_elems(self) -> Vec<T> {
+        let mut elems = self.f.into_unique_elems();
+        elems.extend(self.r.into_unique_elems());
+        elems
+    }
+}
+
+impl<'a, T> traits::Queue<T> for BankersQueue<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn empty() -> Self {
+        BankersQueue::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn enq(&self, x: T) -> Self {
+        self.enq(x)
+    }
+
+    fn try_top(&self) -> Option<&T> {
+        self.try_top()
+    }
+
+    fn try_deq(&self) -> Option<Self> {
+        self.try_deq()
+    }
+
+    fn pop(&self) -> Option<(&T, Self)> {
+        self.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use counted::{Counted, clones};
+    use queue::Queue;
+
+    #[test]
+    fn test_pop() {
+        let q = BankersQueue::<i32>::empty();
+        assert!(q.is_empty());
+        assert!(q.pop().is_none());
+        let q = q.enq(1).enq(2).enq(3);
+        assert_eq!(q.len(), 3);
+        let (x, q) = q.pop().unwrap();
+        assert_eq!(x, &1);
+        let mut q = q.enq(4);
+        let mut v = vec![];
+        while let Some((x, rest)) = q.pop() {
+            v.push(*x);
+            q = rest;
+        }
+        assert_eq!(v, vec![2, 3, 4]);
+        assert!(q.is_empty());
+        assert_eq!(q.len(), 0);
+    }
+
+    #[test]
+    fn test_fifo() {
+        let mut q = BankersQueue::empty();
+        let mut expected = 0;
+        for i in 0..1000 {
+            q = q.enq(i);
+            if i % 3 == 0 {
+                assert_eq!(q.top(), &expected);
+                q = q.deq();
+                expected += 1;
+            }
+        }
+        assert_eq!(q.len(), 1000 - expected as usize);
+        while !q.is_empty() {
+            assert_eq!(q.top(), &expected);
+            q = q.deq();
+            expected += 1;
+        }
+        assert_eq!(expected, 1000);
+    }
+
+    #[test]
+    fn test_persistent_deq() {
+        let n = 1000;
+        let m = 10;
+
+        // Batched queue: Its front list has only one element and the rear list has the rest. Each
+        // dequeue from this version reverses the rear list again.
+        let q = (0..n).fold(Queue::empty(), |q, i| q.enq(Counted(i)));
+        q.deq().top();
+        let before = clones();
+        for _ in 0..m {
+            assert_eq!(q.deq().top(), &Counted(1));
+        }
+        assert!(clones() - before >= m * (n as usize - 1));
+
+        // Banker's queue: The same scenario on every version. Dequeuing from an old version again
+        // reuses the memoized reversal, so each dequeue copies at most a few elements.
+        let mut q = (0..n).fold(BankersQueue::empty(), |q, i| q.enq(Counted(i)));
+        for i in 0..n {
+            if i + 1 < n {
+                q.deq().top();
+                let before = clones();
+                for _ in 0..m {
+                    assert_eq!(q.deq().top(), &Counted(i + 1));
+                }
+                assert!(clones() - before <= 2 * m);
+            }
+            q = q.deq();
+        }
+        assert!(q.is_empty());
+    }
+}
@@ -4,22 +4,27 @@
 
 use std::fmt::Debug;
 use std::cmp::{Ord, Ordering};
-use std::rc::Rc;
 use list::{List, Node};
+use traits;
 
-type TreeNodes<T> = List<TreeNode<T>>;
+type TreeNodes<T, E> = List<TreeNode<T, E>>;
 
+// `extra` is data which a variant of binomial heap attaches to each node. It is `()` for binomial heap
+// and the list of extra elements for skew binomial heap (skewbinheap.rs), which shares trees and
+// their operations with this module.
 #[derive(Clone, Debug)]
-struct TreeNode<T: Clone + Ord + Debug> {
-    val: T,
-    children: TreeNodes<T>,
+pub(crate) struct TreeNode<T: Clone + Ord + Debug, E: Clone + Debug> {
+    pub(crate) val: T,
+    pub(crate) extra: E,
+    pub(crate) children: TreeNodes<T, E>,
 }
 
-impl<T> TreeNode<T>
-where T: Clone + Ord + Debug {
-    fn cons_child(&self, t: &TreeNode<T>) -> TreeNode<T> {
+impl<T, E> TreeNode<T, E>
+where T: Clone + Ord + Debug, E: Clone + Debug {
+    fn cons_child(&self, t: &TreeNode<T, E>) -> TreeNode<T, E> {
         TreeNode {
             val: self.val.clone(),
+            extra: self.extra.clone(),
             children: self.children.cons(t.clone()),
         }
     }
@@ -27,14 +32,20 @@ where T: Clone + Ord + Debug {
 
 // exercise 3.6: remove rank from each node of tree
 #[derive(Clone, Debug)]
-struct Tree<T: Clone + Ord + Debug> {
-    rank: i32,
-    root: TreeNode<T>,
+pub(crate) struct Tree<T: Clone + Ord + Debug, E: Clone + Debug> {
+    pub(crate) rank: i32,
+    pub(crate) root: TreeNode<T, E>,
 }
 
-impl<T> Tree<T>
-where T: Clone + Ord + Debug {
-    fn link(&self, other: &Self) -> Self {
+pub(crate) type Trees<T, E> = List<Tree<T, E>>;
+
+impl<T, E> Tree<T, E>
+where T: Clone + Ord + Debug, E: Clone + Debug {
+    pub(crate) fn leaf(val: T, extra: E) -> Self {
+        Tree {rank: 0, root: TreeNode{val, extra, children: List::empty()}}
+    }
+
+    pub(crate) fn link(&self, other: &Self) -> Self {
         assert_eq!(self.rank, other.rank);
 
         let root = if self.root.val <= other.root.val {
@@ -45,112 +56,120 @@ where T: Clone + Ord + Debug {
 
         Tree {rank: self.rank + 1, root}
     }
-}
-
-type Trees<T> = List<Tree<T>>;
-
-// Binomial heap is a sorted list of binomial trees whose ranks are not the same each other.
-#[derive(Debug, Clone)]
-pub struct BinHeap<T: Clone + Ord + Debug> {
-    trees: Trees<T>,
-}
-
-fn heap<T: Clone + Ord + Debug>(trees: Trees<T>) -> BinHeap<T> {
-    BinHeap {trees}
-}
-
-impl<T> BinHeap<T>
-where T: Clone + Ord + Debug {
-    pub fn empty() -> Self {
-        heap(List::empty())
-    }
 
-    fn insert_tree(t: Tree<T>, ts: &Trees<T>) -> Trees<T> {
+    pub(crate) fn insert_tree(t: Self, ts: &Trees<T, E>) -> Trees<T, E> {
         match *ts.root() {
             Node::Nil => ts.cons(t),
             Node::Cons(ref t2, ref ts2) => {
                 if t.rank < t2.rank {
                     ts.cons(t)
                 } else {
-                    BinHeap::insert_tree(t.link(t2), ts2)
+                    Tree::insert_tree(t.link(t2), ts2)
                 }
             }
         }
     }
 
-    pub fn insert(&self, v: T) -> Self {
-        let t = Tree {
-            rank: 0,
-            root: TreeNode{val: v, children: List::empty()},
-        };
-        heap(BinHeap::insert_tree(t, &self.trees))
-    }
-
-    fn merge_trees(ts1: &Trees<T>, ts2: &Trees<T>) -> Trees<T> {
+    pub(crate) fn merge_trees(ts1: &Trees<T, E>, ts2: &Trees<T, E>) -> Trees<T, E> {
         match (ts1.root(), ts2.root()) {
             (&Node::Nil, _) => ts2.clone(),
             (_, &Node::Nil) => ts1.clone(),
-            (&Node::Cons(ref x1, ref xs1), &Node::Cons(ref x2, ref xs2)) => {
+            (Node::Cons(x1, xs1), Node::Cons(x2, xs2)) => {
                 match x1.rank.cmp(&x2.rank) {
-                    Ordering::Less => BinHeap::merge_trees(xs1, ts2).cons(x1.clone()),
-                    Ordering::Greater => BinHeap::merge_trees(ts1, xs2).cons(x2.clone()),
-                    Ordering::Equal => BinHeap::insert_tree(x1.link(x2), &BinHeap::merge_trees(xs1, xs2)),
+                    Ordering::Less => Tree::merge_trees(xs1, ts2).cons(x1.clone()),
+                    Ordering::Greater => Tree::merge_trees(ts1, xs2).cons(x2.clone()),
+                    Ordering::Equal => Tree::insert_tree(x1.link(x2), &Tree::merge_trees(xs1, xs2)),
                 }
             },
         }
     }
 
-    pub fn merge(&self, other: &Self) -> Self {
-        heap(BinHeap::merge_trees(&self.trees, &other.trees))
+    pub(crate) fn find_min_root(trees: &Trees<T, E>) -> Option<&Self> {
+        // Note: min_by() returns the first one when several trees have the same minimum.
+        trees.iter().min_by(|t1, t2| t1.root.val.cmp(&t2.root.val))
     }
 
-    fn find_min_root(trees: &Trees<T>) -> &Tree<T> {
+    pub(crate) fn remove_min_root(trees: &Trees<T, E>) -> Option<(&Self, Trees<T, E>)> {
         match *trees.root() {
-            Node::Nil => panic!("No tree in heap!"),
-            Node::Cons(ref t, ref ts) if ts.is_empty() => t,
-            Node::Cons(ref t, ref ts) => {
-                let t2 = BinHeap::find_min_root(ts);
-                if t.root.val <= t2.root.val { t } else { t2 }
+            Node::Nil => None,
+            Node::Cons(ref t, ref ts) => match Tree::remove_min_root(ts) {
+                Some((t2, ts2)) if t2.root.val < t.root.val => Some((t2, ts2.cons(t.clone()))),
+                _ => Some((t, ts.clone())),
             },
         }
     }
 
+    // Children are ordered by decreasing rank (rank - 1, rank - 2, ..., 0). Consing them one by one
+    // reverses them into increasing rank order as well as the rest of trees.
+    pub(crate) fn nodes_to_trees(rank: i32, ts: &TreeNodes<T, E>) -> Trees<T, E> {
+        ts.iter()
+            .zip((0..rank + 1).rev())
+            .fold(List::empty(), |acc, (t, rank)| acc.cons(Tree{rank, root: t.clone()}))
+    }
+}
+
+// Binomial heap is a sorted list of binomial trees whose ranks are not the same each other.
+#[derive(Debug, Clone)]
+pub struct BinHeap<T: Clone + Ord + Debug> {
+    trees: Trees<T, ()>,
+}
+
+fn heap<T: Clone + Ord + Debug>(trees: Trees<T, ()>) -> BinHeap<T> {
+    BinHeap {trees}
+}
+
+impl<T> BinHeap<T>
+where T: Clone + Ord + Debug {
+    pub fn empty() -> Self {
+        heap(List::empty())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trees.is_empty()
+    }
+
+    // O(log n): A tree of rank r contains 2^r elements.
+    pub fn len(&self) -> usize {
+        self.trees.iter().map(|t| 1 << t.rank).sum()
+    }
+
+    // Iterates elements in ascending order
+    pub fn iter(&self) -> traits::HeapIter<T, Self> {
+        traits::HeapIter::new(self.clone())
+    }
+
+    pub fn insert(&self, v: T) -> Self {
+        heap(Tree::insert_tree(Tree::leaf(v, ()), &self.trees))
+    }
+
+    pub fn merge(&self, other: &Self) -> Self {
+        heap(Tree::merge_trees(&self.trees, &other.trees))
+    }
+
     // exercise 3.5: Implement find_min without remove_min_root
+    pub fn try_find_min(&self) -> Option<&T> {
+        Tree::find_min_root(&self.trees).map(|t| &t.root.val)
+    }
+
     pub fn find_min(&self) -> &T {
-        &BinHeap::find_min_root(&self.trees).root.val
+        self.try_find_min().expect("heap is empty!")
     }
 
-    fn remove_min_root(trees: &Trees<T>) -> (&Tree<T>, Trees<T>) {
-        match *trees.root() {
-            Node::Nil => panic!("No tree in heap!"),
-            Node::Cons(ref t, ref ts) if ts.is_empty() => (t, List::empty()),
-            Node::Cons(ref t, ref ts) => {
-                let (t2, ts2) = BinHeap::remove_min_root(ts);
-                if t.root.val <= t2.root.val {
-                    (t, ts.clone())
-                } else {
-                    (t2, ts2.cons(t.clone()))
-                }
-            }
-        }
+    pub fn pop_min(&self) -> Option<(&T, Self)> {
+        Tree::remove_min_root(&self.trees).map(|(t, ts1)| {
+            // Note: Rust's pattern cannot contain both by-ref and by-move binding at the same time.
+            let Tree{ref rank, root: TreeNode{ref val, ref children, ..}} = *t;
+            let ts2 = Tree::nodes_to_trees(rank - 1, children);
+            (val, heap(Tree::merge_trees(&ts1, &ts2)))
+        })
     }
 
-    fn nodes_to_trees(rank: i32, ts: &TreeNodes<T>) -> Trees<T> {
-        match *ts.root() {
-            Node::Nil => List::empty(),
-            Node::Cons(ref t, ref ts) => {
-                let ts = BinHeap::nodes_to_trees(rank, ts);
-                List(Rc::new(Node::Cons(Tree{rank, root: t.clone()}, ts)))
-            },
-        }
+    pub fn try_delete_min(&self) -> Option<Self> {
+        self.pop_min().map(|(_, h)| h)
     }
 
     pub fn delete_min(&self) -> Self {
-        // Note: Rust's pattern cannot contain both by-ref and by-move binding at the same time.
-        let (t, ts1) = BinHeap::remove_min_root(&self.trees);
-        let &Tree{ref rank, root: TreeNode{val: _, ref children}} = t;
-        let ts2 = BinHeap::nodes_to_trees(rank - 1, children);
-        heap(BinHeap::merge_trees(&ts1.rev(), &ts2))
+        self.try_delete_min().expect("heap is empty!")
     }
 }
 
@@ -168,7 +187,16 @@ where T: Clone + Ord + Debug {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.min == None
+        self.min.is_none()
+    }
+
+    pub fn len(&self) -> usize {
+        if self.is_empty() { 0 } else { self.rest.len() + 1 }
+    }
+
+    // Iterates elements in ascending order
+    pub fn iter(&self) -> traits::HeapIter<T, Self> {
+        traits::HeapIter::new(self.clone())
     }
 
     pub fn insert(&self, v: T) -> Self {
@@ -180,6 +208,13 @@ where T: Clone + Ord + Debug {
     }
 
     pub fn merge(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            return other.clone();
+        }
+        if other.is_empty() {
+            return self.clone();
+        }
+
         if self.min < other.min {
             BinHeap2 {
                 min: Some(self.min.clone().unwrap()),
@@ -193,22 +228,101 @@ where T: Clone + Ord + Debug {
         }
     }
 
+    pub fn try_find_min(&self) -> Option<&T> {
+        self.min.as_ref()
+    }
+
     pub fn find_min(&self) -> &T {
-        match &self.min {
-            &Some(ref min) => min,
-            &None => panic!("heap is empty!"),
+        self.try_find_min().expect("heap is empty!")
+    }
+
+    pub fn try_delete_min(&self) -> Option<Self> {
+        self.min.as_ref()?;
+        match self.rest.pop_min() {
+            None => Some(BinHeap2::empty()),
+            Some((min, rest)) => Some(BinHeap2 {min: Some(min.clone()), rest}),
         }
     }
 
     pub fn delete_min(&self) -> Self {
-        if self.rest.trees.is_empty() {
-            assert!(self.min.is_some());
-            BinHeap2::empty()
-        } else {
-            let min = Some(self.rest.find_min().clone());
-            BinHeap2 {min, rest: self.rest.delete_min()}
-        }
+        self.try_delete_min().expect("heap is empty!")
+    }
+
+    pub fn pop_min(&self) -> Option<(&T, Self)> {
+        self.try_find_min().and_then(|x| self.try_delete_min().map(|h| (x, h)))
+    }
+}
+
+impl<T> traits::Heap<T> for BinHeap<T>
+where T: Clone + Ord + Debug {
+    fn empty() -> Self {
+        BinHeap::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn insert(&self, x: T) -> Self {
+        self.insert(x)
+    }
+
+    fn merge(&self, other: &Self) -> Self {
+        self.merge(other)
+    }
+
+    fn try_find_min(&self) -> Option<&T> {
+        self.try_find_min()
+    }
+
+    fn try_delete_min(&self) -> Option<Self> {
+        self.try_delete_min()
     }
+
+    fn pop_min(&self) -> Option<(&T, Self)> {
+        self.pop_min()
+    }
+}
+
+impl<T> traits::HeapFamily for BinHeap<T>
+where T: Clone + Ord + Debug {
+    type Heap<E: Clone + Ord + Debug> = BinHeap<E>;
+}
+
+impl<T> traits::Heap<T> for BinHeap2<T>
+where T: Clone + Ord + Debug {
+    fn empty() -> Self {
+        BinHeap2::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn insert(&self, x: T) -> Self {
+        self.insert(x)
+    }
+
+    fn merge(&self, other: &Self) -> Self {
+        self.merge(other)
+    }
+
+    fn try_find_min(&self) -> Option<&T> {
+        self.try_find_min()
+    }
+
+    fn try_delete_min(&self) -> Option<Self> {
+        self.try_delete_min()
+    }
+
+    fn pop_min(&self) -> Option<(&T, Self)> {
+        self.pop_min()
+    }
+}
+
+impl<T> traits::HeapFamily for BinHeap2<T>
+where T: Clone + Ord + Debug {
+    type Heap<E: Clone + Ord + Debug> = BinHeap2<E>;
 }
 
 
@@ -249,6 +363,47 @@ mod tests {
         assert!(h.trees.is_empty());
     }
 
+    #[test]
+    fn test_delete_min() {
+        let mut h = BinHeap::empty();
+        for i in (0..20).rev() {
+            h = h.insert(i);
+        }
+        for i in 0..20 {
+            assert_eq!(h.find_min(), &i);
+            h = h.delete_min();
+        }
+        assert!(h.is_empty());
+    }
+
+    #[test]
+    fn test_pop_min() {
+        let h = BinHeap::<i32>::empty();
+        assert!(h.try_find_min().is_none());
+        assert!(h.try_delete_min().is_none());
+        assert!(h.pop_min().is_none());
+        let h = h.insert(3).insert(1).insert(2);
+        let (x, h) = h.pop_min().unwrap();
+        assert_eq!(x, &1);
+        assert_eq!(h.try_find_min(), Some(&2));
+    }
+
+    #[test]
+    fn test_len() {
+        let h = BinHeap::empty().insert(3).insert(1).insert(7);
+        assert_eq!(h.len(), 3);
+        assert_eq!(h.merge(&h).len(), 6);
+        assert_eq!(h.delete_min().len(), 2);
+        assert_eq!(BinHeap::<i32>::empty().len(), 0);
+    }
+
+    #[test]
+    fn test_iter() {
+        let h = BinHeap::empty().insert(3).insert(1).insert(7).insert(10);
+        assert_eq!(h.iter().collect::<Vec<_>>(), vec![1, 3, 7, 10]);
+        assert_eq!(h.find_min(), &1);
+    }
+
     #[test]
     fn test_empty2() {
         let h = BinHeap2::<i32>::empty();
@@ -281,4 +436,25 @@ mod tests {
         }
         assert!(h.is_empty());
     }
+
+    #[test]
+    fn test_len2() {
+        let h = BinHeap2::empty().insert(3).insert(1).insert(7);
+        assert_eq!(h.len(), 3);
+        assert_eq!(h.delete_min().len(), 2);
+        assert_eq!(h.iter().collect::<Vec<_>>(), vec![1, 3, 7]);
+        assert_eq!(BinHeap2::<i32>::empty().len(), 0);
+    }
+
+    #[test]
+    fn test_pop_min2() {
+        let h = BinHeap2::<i32>::empty();
+        assert!(h.try_find_min().is_none());
+        assert!(h.try_delete_min().is_none());
+        assert!(h.pop_min().is_none());
+        let h = h.insert(3).insert(1).insert(2);
+        let (x, h) = h.pop_min().unwrap();
+        assert_eq!(x, &1);
+        assert_eq!(h.try_find_min(), Some(&2));
+    }
 }
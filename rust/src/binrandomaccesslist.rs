@@ -0,0 +1,308 @@
+// Section 9.2.1
+//
+// Binary random-access list.
+// O(log n) cons, head, tail, lookup and update
+
+use std::fmt::Debug;
+use std::iter::FromIterator;
+use list::{self, List, Node};
+use ptr::Ptr;
+use traits;
+
+// Complete binary leaf tree. A node caches the number of leaves
+#[derive(Clone, Debug)]
+enum Tree<T: Clone + Debug> {
+    Leaf(T),
+    Node(usize, Link<T>, Link<T>),
+}
+
+type Link<T> = Ptr<Tree<T>>;
+
+impl<T> Tree<T>
+where T: Clone + Debug {
+    fn size(&self) -> usize {
+        match *self {
+            Tree::Leaf(_) => 1,
+            Tree::Node(w, _, _) => w,
+        }
+    }
+
+    fn link(t1: &Link<T>, t2: &Link<T>) -> Link<T> {
+        Ptr::new(Tree::Node(t1.size() + t2.size(), t1.clone(), t2.clone()))
+    }
+
+    fn lookup(&self, mut i: usize) -> &T {
+        let mut t = self;
+        loop {
+            match *t {
+                Tree::Leaf(ref x) => return x,
+                Tree::Node(w, ref t1, ref t2) => {
+                    if i < w / 2 {
+                        t = t1;
+                    } else {
+                        i -= w / 2;
+                        t = t2;
+                    }
+                },
+            }
+        }
+    }
+
+    // Only nodes on the path to the i-th leaf are copied
+    fn update(&self, i: usize, x: T) -> Self {
+        match *self {
+            Tree::Leaf(_) => Tree::Leaf(x),
+            Tree::Node(w, ref t1, ref t2) => {
+                if i < w / 2 {
+                    Tree::Node(w, Ptr::new(t1.update(i, x)), t2.clone())
+                } else {
+                    Tree::Node(w, t1.clone(), Ptr::new(t2.update(i - w / 2, x)))
+                }
+            },
+        }
+    }
+}
+
+// A digit at position k is One when the list contains a tree of 2^k elements
+#[derive(Clone, Debug)]
+enum Digit<T: Clone + Debug> {
+    Zero,
+    One(Link<T>),
+}
+
+type Digits<T> = List<Digit<T>>;
+
+// Note:
+// The spine is a list of digits in increasing order of weight. Elements are ordered from the tree of
+// the lowest digit, and from left to right in each tree. `cons` and `tail` correspond to increment
+// and decrement of binary numbers. The last digit is always One so that the spine has no trailing
+// zeros.
+#[derive(Clone, Debug)]
+pub struct BinRandomAccessList<T: Clone + Debug> {
+    digits: Digits<T>,
+}
+
+impl<T> BinRandomAccessList<T>
+where T: Clone + Debug {
+    pub fn empty() -> Self {
+        BinRandomAccessList{digits: List::empty()}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    // O(log n): Sizes of trees are cached.
+    pub fn len(&self) -> usize {
+        self.digits.iter().map(|d| match *d {
+            Digit::Zero => 0,
+            Digit::One(ref t) => t.size(),
+        }).sum()
+    }
+
+    fn cons_tree(t: Link<T>, ds: &Digits<T>) -> Digits<T> {
+        match *ds.root() {
+            Node::Nil => List::one(Digit::One(t)),
+            Node::Cons(Digit::Zero, ref ds) => ds.cons(Digit::One(t)),
+            Node::Cons(Digit::One(ref t2), ref ds) => {
+                BinRandomAccessList::cons_tree(Tree::link(&t, t2), ds).cons(Digit::Zero)
+            },
+        }
+    }
+
+    // The digits must not be empty
+    fn uncons_tree(ds: &Digits<T>) -> (Link<T>, Digits<T>) {
+        match *ds.root() {
+            Node::Nil => unreachable!("digits are not empty"),
+            Node::Cons(Digit::One(ref t), ref ds) if ds.is_empty() => (t.clone(), List::empty()),
+            Node::Cons(Digit::One(ref t), ref ds) => (t.clone(), ds.cons(Digit::Zero)),
+            Node::Cons(Digit::Zero, ref ds) => {
+                let (t, ds) = BinRandomAccessList::uncons_tree(ds);
+                match *t {
+                    Tree::Node(_, ref t1, ref t2) => (t1.clone(), ds.cons(Digit::One(t2.clone()))),
+                    Tree::Leaf(_) => unreachable!("tree at higher digit is not a leaf"),
+                }
+            },
+        }
+    }
+
+    pub fn cons(&self, x: T) -> Self {
+        BinRandomAccessList{digits: BinRandomAccessList::cons_tree(Ptr::new(Tree::Leaf(x)), &self.digits)}
+    }
+
+    // The first element is the leftmost leaf of the first tree
+    pub fn try_head(&self) -> Option<&T> {
+        self.digits.iter().filter_map(|d| match *d {
+            Digit::Zero => None,
+            Digit::One(ref t) => Some(t.lookup(0)),
+        }).next()
+    }
+
+    pub fn try_tail(&self) -> Option<Self> {
+        if self.is_empty() {
+            return None;
+        }
+        let (_, digits) = BinRandomAccessList::uncons_tree(&self.digits);
+        Some(BinRandomAccessList{digits})
+    }
+
+    pub fn uncons(&self) -> Option<(&T, Self)> {
+        self.try_head().and_then(|x| self.try_tail().map(|xs| (x, xs)))
+    }
+
+    pub fn head(&self) -> &T {
+        self.try_head().expect("List is empty!")
+    }
+
+    pub fn tail(&self) -> Self {
+        self.try_tail().expect("List is empty!")
+    }
+
+    pub fn try_lookup(&self, mut i: usize) -> Option<&T> {
+        for d in self.digits.iter() {
+            if let Digit::One(ref t) = *d {
+                if i < t.size() {
+                    return Some(t.lookup(i));
+                }
+                i -= t.size();
+            }
+        }
+        None
+    }
+
+    fn update_digits(i: usize, x: T, ds: &Digits<T>) -> Option<Digits<T>> {
+        match *ds.root() {
+            Node::Nil => None,
+            Node::Cons(Digit::Zero, ref ds) => {
+                BinRandomAccessList::update_digits(i, x, ds).map(|ds| ds.cons(Digit::Zero))
+            },
+            Node::Cons(Digit::One(ref t), ref ds) => {
+                if i < t.size() {
+                    Some(ds.cons(Digit::One(Ptr::new(t.update(i, x)))))
+                } else {
+                    BinRandomAccessList::update_digits(i - t.size(), x, ds).map(|ds| ds.cons(Digit::One(t.clone())))
+                }
+            },
+        }
+    }
+
+    // Trees other than the one containing the i-th element are shared
+    pub fn try_update(&self, i: usize, x: T) -> Option<Self> {
+        BinRandomAccessList::update_digits(i, x, &self.digits).map(|digits| BinRandomAccessList{digits})
+    }
+
+    pub fn lookup(&self, i: usize) -> &T {
+        self.try_lookup(i).expect("Index out of bounds!")
+    }
+
+    pub fn update(&self, i: usize, x: T) -> Self {
+        self.try_update(i, x).expect("Index out of bounds!")
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter{digits: self.digits.iter(), stack: vec![]}
+    }
+}
+
+pub struct Iter<'a, T: 'a + Clone + Debug> {
+    digits: list::Iter<'a, Digit<T>>,
+    // Right subtrees which are not visited yet
+    stack: Vec<&'a Tree<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T>
+where T: Clone + Debug {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut t = match self.stack.pop() {
+            Some(t) => t,
+            None => loop {
+                match self.digits.next() {
+                    Some(Digit::One(t)) => break t,
+                    Some(Digit::Zero) => continue,
+                    None => return None,
+                }
+            },
+        };
+        loop {
+            match *t {
+                Tree::Leaf(ref x) => return Some(x),
+                Tree::Node(_, ref t1, ref t2) => {
+                    self.stack.push(t2);
+                    t = t1;
+                },
+            }
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a BinRandomAccessList<T>
+where T: Clone + Debug {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> FromIterator<T> for BinRandomAccessList<T>
+where T: Clone + Debug {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let xs = iter.into_iter().collect::<Vec<_>>();
+        xs.into_iter().rev().fold(BinRandomAccessList::empty(), |l, x| l.cons(x))
+    }
+}
+
+impl<T> traits::Stack<T> for BinRandomAccessList<T>
+where T: Clone + Debug {
+    fn empty() -> Self {
+        BinRandomAccessList::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn cons(&self, x: T) -> Self {
+        self.cons(x)
+    }
+
+    fn try_head(&self) -> Option<&T> {
+        self.try_head()
+    }
+
+    fn try_tail(&self) -> Option<Self> {
+        self.try_tail()
+    }
+
+    fn uncons(&self) -> Option<(&T, Self)> {
+        self.uncons()
+    }
+}
+
+impl<T> traits::RandomAccessList<T> for BinRandomAccessList<T>
+where T: Clone + Debug {
+    fn try_lookup(&self, i: usize) -> Option<&T> {
+        self.try_lookup(i)
+    }
+
+    fn try_update(&self, i: usize, x: T) -> Option<Self> {
+        self.try_update(i, x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_len_iter() {
+        for n in 0..70 {
+            let l = (0..n).collect::<BinRandomAccessList<_>>();
+            assert_eq!(l.len(), n);
+            assert!(l.iter().cloned().eq(0..n));
+        }
+    }
+}
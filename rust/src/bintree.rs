@@ -1,36 +1,32 @@
 // p.21 - p.25
 
 use std::fmt::Debug;
-use std::rc::Rc;
 use std::cmp::PartialOrd;
+use ptr::Ptr;
+use traits;
 
 // left <= right
 #[derive(Clone, PartialEq, Debug)]
-enum BinTree<T: Clone + PartialOrd + Debug> {
+enum Node<T: Clone + PartialOrd + Debug> {
     Leaf,
-    Knot(T, Rc<BinTree<T>>, Rc<BinTree<T>>),
+    Knot(T, Link<T>, Link<T>),
 }
 
-impl<T> BinTree<T>
-where T: Clone + PartialOrd + Debug {
-    pub fn from_array(arr: &[T]) -> Self {
-        if arr.len() == 0 {
-            BinTree::Leaf
-        } else {
-            BinTree::from_array(&arr[1..]).insert(arr[0].clone())
-        }
-    }
+type Link<T> = Ptr<Node<T>>;
 
+impl<T> Node<T>
+where T: Clone + PartialOrd + Debug {
+    #[allow(clippy::neg_cmp_op_on_partial_ord)]
     fn member_impl(&self, v: &T, memo: Option<&T>) -> bool {
         match *self {
-            BinTree::Leaf => {
+            Node::Leaf => {
                 match memo {
                     // We already know v >= x. !(x < v) means x == v
                     Some(x) => !(x < v),
                     None => false,
                 }
             },
-            BinTree::Knot(ref x, ref l, ref r) => {
+            Node::Knot(ref x, ref l, ref r) => {
                 // Compare element at once. It makes `member` d + 1 (not 2d) where d is depth.
                 if v < x {
                     l.member_impl(v, memo)
@@ -41,42 +37,131 @@ where T: Clone + PartialOrd + Debug {
         }
     }
 
-    pub fn member(&self, v: &T) -> bool {
-        self.member_impl(v, None)
-    }
-
+    #[allow(clippy::neg_cmp_op_on_partial_ord)]
     fn insert_impl(&self, v: T, memo: Option<&T>) -> Option<Self> {
         match *self {
-            BinTree::Leaf => {
+            Node::Leaf => {
                 // exercise 2.4: Check memo to know there is an element which is equivalent to `v`.
                 // exercise 2.3: If the tree already has the value, we don't need to clone tree at all.
                 match memo {
                     Some(x) if !(x < &v) => None,
-                    _ => Some(BinTree::Knot(v, Rc::new(BinTree::Leaf), Rc::new(BinTree::Leaf))),
+                    _ => Some(Node::Knot(v, Ptr::new(Node::Leaf), Ptr::new(Node::Leaf))),
                 }
             },
-            BinTree::Knot(ref x, ref l, ref r) => {
+            Node::Knot(ref x, ref l, ref r) => {
                 // exercise 2.4: Makes number of comparing element d + 1 (not 2d) where d is depth.
                 if &v < x {
                     // Right of the node can be shared.
-                    l.insert_impl(v, memo).map(|l| BinTree::Knot(x.clone(), Rc::new(l), r.clone()))
+                    l.insert_impl(v, memo).map(|l| Node::Knot(x.clone(), Ptr::new(l), r.clone()))
                 } else {
                     // Left of the node can be shared.
-                    r.insert_impl(v, Some(x)).map(|r| BinTree::Knot(x.clone(), l.clone(), Rc::new(r)))
+                    r.insert_impl(v, Some(x)).map(|r| Node::Knot(x.clone(), l.clone(), Ptr::new(r)))
                 }
             },
         }
     }
+}
+
+// Unbalanced binary search tree. Its nodes are hidden and it caches the number of elements.
+#[derive(Clone, Debug)]
+pub struct BinTree<T: Clone + PartialOrd + Debug> {
+    root: Link<T>,
+    size: usize,
+}
+
+impl<T> BinTree<T>
+where T: Clone + PartialOrd + Debug {
+    pub fn empty() -> Self {
+        BinTree{root: Ptr::new(Node::Leaf), size: 0}
+    }
 
-    pub fn insert(self, v: T) -> Self {
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn from_array(arr: &[T]) -> Self {
+        arr.iter().rev().fold(BinTree::empty(), |t, x| t.insert(x.clone()))
+    }
+
+    pub fn member(&self, v: &T) -> bool {
+        self.root.member_impl(v, None)
+    }
+
+    pub fn insert(&self, v: T) -> Self {
         // exercise 2.3: If the tree already has the value, we don't need to clone tree at all.
-        match (&self).insert_impl(v, None) {
-            Some(t) => t,
-            None => self,
+        match self.root.insert_impl(v, None) {
+            Some(n) => BinTree{root: Ptr::new(n), size: self.size + 1},
+            None => self.clone(),
+        }
+    }
+
+    // Iterates elements in sorted order
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut it = Iter{stack: vec![]};
+        it.push_lefts(&self.root);
+        it
+    }
+}
+
+// In-order traversal. The stack holds nodes whose left subtrees were already visited.
+pub struct Iter<'a, T: 'a + Clone + PartialOrd + Debug> {
+    stack: Vec<&'a Node<T>>,
+}
+
+impl<'a, T> Iter<'a, T>
+where T: Clone + PartialOrd + Debug {
+    fn push_lefts(&mut self, mut node: &'a Node<T>) {
+        while let Node::Knot(_, ref l, _) = *node {
+            self.stack.push(node);
+            node = l;
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T>
+where T: Clone + PartialOrd + Debug {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.stack.pop() {
+            Some(Node::Knot(x, _, r)) => {
+                self.push_lefts(r);
+                Some(x)
+            },
+            _ => None,
         }
     }
 }
 
+impl<'a, T> IntoIterator for &'a BinTree<T>
+where T: Clone + PartialOrd + Debug {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> traits::Set<T> for BinTree<T>
+where T: Clone + PartialOrd + Debug {
+    fn empty() -> Self {
+        BinTree::empty()
+    }
+
+    fn insert(&self, x: T) -> Self {
+        self.insert(x)
+    }
+
+    fn member(&self, x: &T) -> bool {
+        self.member(x)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,4 +183,29 @@ mod tests {
         let t = t.insert(2);
         assert!(t.member(&2));
     }
+
+    #[test]
+    fn test_len() {
+        let t = BinTree::empty();
+        assert!(t.is_empty());
+        assert_eq!(t.len(), 0);
+        let t = t.insert(3).insert(1).insert(2);
+        assert!(!t.is_empty());
+        assert_eq!(t.len(), 3);
+        // Inserting existing element does not change its size
+        let t = t.insert(2);
+        assert_eq!(t.len(), 3);
+    }
+
+    #[test]
+    fn test_iter() {
+        let t = BinTree::from_array(&[5, 2, 4, 1, 6, 7]);
+        assert_eq!(t.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 4, 5, 6, 7]);
+        let mut v = vec![];
+        for x in &t {
+            v.push(*x);
+        }
+        assert_eq!(v, vec![1, 2, 4, 5, 6, 7]);
+        assert!(BinTree::<i32>::empty().iter().next().is_none());
+    }
 }
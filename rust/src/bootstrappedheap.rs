@@ -0,0 +1,271 @@
+// Section 10.2.2
+//
+// Bootstrapped heap (heaps with efficient merging).
+// O(1) insert, merge and find_min, and delete_min in the time of delete_min of the primitive heap
+
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+use std::mem;
+use ptr::Ptr;
+use skewbinheap::SkewBinHeap;
+use traits::{self, HeapFamily};
+
+// Note:
+// A non-empty heap is its minimum element and a primitive heap of non-empty bootstrapped heaps, which
+// are ordered by their minimum elements. Merging two heaps inserts the one with the larger minimum into
+// the primitive heap of the other, so `merge` takes the time of `insert` of the primitive heap.
+// With skew binomial heap (the default) it is worst-case O(1). With binomial heap it is amortized O(1)
+// unless old versions are used persistently.
+pub struct BootstrappedHeap<T: Clone + Ord + Debug, P: HeapFamily = SkewBinHeap<T>> {
+    size: usize,
+    root: Option<Root<T, P>>,
+    family: PhantomData<P>,
+}
+
+type Root<T, P> = Ptr<(T, <P as HeapFamily>::Heap<BootstrappedHeap<T, P>>)>;
+
+// Implemented manually since deriving them requires P itself to implement them.
+impl<T, P> Clone for BootstrappedHeap<T, P>
+where T: Clone + Ord + Debug, P: HeapFamily {
+    fn clone(&self) -> Self {
+        BootstrappedHeap{size: self.size, root: self.root.clone(), family: PhantomData}
+    }
+}
+
+impl<T, P> Debug for BootstrappedHeap<T, P>
+where T: Clone + Ord + Debug, P: HeapFamily {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BootstrappedHeap").field("size", &self.size).field("root", &self.root).finish()
+    }
+}
+
+// Heaps are compared by their minimum elements so that they can be elements of the primitive heap
+impl<T, P> PartialEq for BootstrappedHeap<T, P>
+where T: Clone + Ord + Debug, P: HeapFamily {
+    fn eq(&self, other: &Self) -> bool {
+        self.try_find_min() == other.try_find_min()
+    }
+}
+
+impl<T, P> Eq for BootstrappedHeap<T, P>
+where T: Clone + Ord + Debug, P: HeapFamily {}
+
+impl<T, P> PartialOrd for BootstrappedHeap<T, P>
+where T: Clone + Ord + Debug, P: HeapFamily {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, P> Ord for BootstrappedHeap<T, P>
+where T: Clone + Ord + Debug, P: HeapFamily {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.try_find_min().cmp(&other.try_find_min())
+    }
+}
+
+impl<T, P> BootstrappedHeap<T, P>
+where T: Clone + Ord + Debug, P: HeapFamily {
+    pub fn empty() -> Self {
+        BootstrappedHeap{size: 0, root: None, family: PhantomData}
+    }
+
+    fn node(size: usize, x: T, p: P::Heap<Self>) -> Self {
+        BootstrappedHeap{size, root: Some(Ptr::new((x, p))), family: PhantomData}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    // Iterates elements in ascending order
+    pub fn iter(&self) -> traits::HeapIter<T, Self> {
+        traits::HeapIter::new(self.clone())
+    }
+
+    pub fn merge(&self, other: &Self) -> Self {
+        let size = self.size + other.size;
+        match (&self.root, &other.root) {
+            (_, None) => self.clone(),
+            (None, _) => other.clone(),
+            (Some(r1), Some(r2)) => {
+                let ((x, p1), (y, p2)) = (&**r1, &**r2);
+                if x <= y {
+                    BootstrappedHeap::node(size, x.clone(), traits::Heap::insert(p1, other.clone()))
+                } else {
+                    BootstrappedHeap::node(size, y.clone(), traits::Heap::insert(p2, self.clone()))
+                }
+            },
+        }
+    }
+
+    pub fn insert(&self, x: T) -> Self {
+        BootstrappedHeap::node(1, x, traits::Heap::empty()).merge(self)
+    }
+
+    pub fn try_find_min(&self) -> Option<&T> {
+        self.root.as_ref().map(|r| &r.0)
+    }
+
+    pub fn find_min(&self) -> &T {
+        self.try_find_min().expect("heap is empty!")
+    }
+
+    // The heap with the minimum element in the primitive heap becomes the new root and its primitive
+    // heap is merged with the rest
+    pub fn pop_min(&self) -> Option<(&T, Self)> {
+        self.root.as_ref().map(|r| {
+            let (ref x, ref p) = **r;
+            let h = match traits::Heap::pop_min(p) {
+                None => BootstrappedHeap::empty(),
+                Some((h, p2)) => {
+                    let (ref y, ref p1) = **h.root.as_ref().expect("heaps in primitive heap are not empty");
+                    BootstrappedHeap::node(self.size - 1, y.clone(), traits::Heap::merge(p1, &p2))
+                },
+            };
+            (x, h)
+        })
+    }
+
+    pub fn try_delete_min(&self) -> Option<Self> {
+        self.pop_min().map(|(_, h)| h)
+    }
+
+    pub fn delete_min(&self) -> Self {
+        self.try_delete_min().expect("heap is empty!")
+    }
+}
+
+// Inserting elements in descending order nests each heap in the primitive heap of the next one, and
+// dropping such a heap recursively overflows the stack. Roots owned only by the dropped heap are
+// unlinked in a loop: heaps are popped from a unique root's primitive heap while they are owned only
+// by it. The first shared one means the rest is likely shared with another version (e.g. the result
+// of `delete_min`), so the rest is dropped as usual instead of popping all of it.
+impl<T, P> Drop for BootstrappedHeap<T, P>
+where T: Clone + Ord + Debug, P: HeapFamily {
+    fn drop(&mut self) {
+        let mut roots = self.root.take().into_iter().collect::<Vec<_>>();
+        while let Some(mut r) = roots.pop() {
+            let mut p = match Ptr::get_mut(&mut r) {
+                Some((_, p)) => mem::replace(p, traits::Heap::empty()),
+                None => continue,
+            };
+            loop {
+                let (mut c, rest) = match traits::Heap::pop_min(&p) {
+                    Some((h, rest)) => (h.root.clone().expect("heaps in primitive heap are not empty"), rest),
+                    None => break,
+                };
+                // Dropping the old primitive heap leaves the clone of the popped root
+                p = rest;
+                if Ptr::get_mut(&mut c).is_none() {
+                    break;
+                }
+                roots.push(c);
+            }
+        }
+    }
+}
+
+impl<T, P> traits::Heap<T> for BootstrappedHeap<T, P>
+where T: Clone + Ord + Debug, P: HeapFamily {
+    fn empty() -> Self {
+        BootstrappedHeap::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn insert(&self, x: T) -> Self {
+        self.insert(x)
+    }
+
+    fn merge(&self, other: &Self) -> Self {
+        self.merge(other)
+    }
+
+    fn try_find_min(&self) -> Option<&T> {
+        self.try_find_min()
+    }
+
+    fn try_delete_min(&self) -> Option<Self> {
+        self.try_delete_min()
+    }
+
+    fn pop_min(&self) -> Option<(&T, Self)> {
+        self.pop_min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use binheap::BinHeap;
+    use leftheap::LeftHeap;
+
+    fn check_sort<P: HeapFamily>() {
+        let xs = (0..500).map(|i| i * 37 % 101).collect::<Vec<_>>();
+        let h = xs.iter().fold(BootstrappedHeap::<i32, P>::empty(), |h, &x| h.insert(x));
+        assert_eq!(h.len(), 500);
+        let mut sorted = xs.clone();
+        sorted.sort();
+        assert_eq!(h.iter().collect::<Vec<_>>(), sorted);
+    }
+
+    #[test]
+    fn test_primitive_heaps() {
+        check_sort::<SkewBinHeap<i32>>();
+        check_sort::<BinHeap<i32>>();
+        check_sort::<LeftHeap<i32>>();
+    }
+
+    #[test]
+    fn test_merge() {
+        let h1 = BootstrappedHeap::<_>::empty().insert(3).insert(1).insert(7).insert(10);
+        let h2 = BootstrappedHeap::empty().insert(2).insert(4).insert(11).insert(0);
+        let h = h1.merge(&h2);
+        assert_eq!(h.len(), 8);
+        assert_eq!(h.find_min(), &0);
+        assert_eq!(h.iter().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 7, 10, 11]);
+        assert_eq!(h2.merge(&h1).iter().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 7, 10, 11]);
+        // Operands are not changed
+        assert_eq!(h1.iter().collect::<Vec<_>>(), vec![1, 3, 7, 10]);
+        assert!(h.merge(&BootstrappedHeap::empty()).iter().eq(h.iter()));
+    }
+
+    #[test]
+    fn test_long() {
+        // Descending insertion nests every heap in the primitive heap of the next one
+        let n = 100000;
+        let h = (0..n).rev().fold(BootstrappedHeap::<_>::empty(), |h, i| h.insert(i));
+        assert_eq!(h.len(), n as usize);
+        assert!(h.iter().eq(0..n));
+        let h = (0..n).rev().fold(BootstrappedHeap::<_, BinHeap<i32>>::empty(), |h, i| h.insert(i));
+        drop(h);
+    }
+
+    #[test]
+    fn test_many_merges() {
+        let mut h = BootstrappedHeap::<i32, BinHeap<i32>>::empty();
+        let mut v = vec![];
+        for i in 0..200 {
+            let xs = (0..i % 7).map(|j| (i * 13 + j * 29) % 97).collect::<Vec<_>>();
+            h = h.merge(&xs.iter().fold(BootstrappedHeap::empty(), |h, &x| h.insert(x)));
+            v.extend(xs);
+            if i % 4 == 0 {
+                v.sort();
+                if !v.is_empty() {
+                    assert_eq!(h.find_min(), &v.remove(0));
+                    h = h.delete_min();
+                }
+            }
+        }
+        v.sort();
+        assert_eq!(h.iter().collect::<Vec<_>>(), v);
+    }
+}
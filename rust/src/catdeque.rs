@@ -0,0 +1,960 @@
+// Section 11.2
+//
+// Catenable deques.
+// SimpleCatDeque: Amortized O(1) deque operations and amortized O(log n) append
+// CatDeque: Amortized O(1) deque operations and append (Kaplan and Tarjan)
+//
+// Both are built on `deque::Deque` by implicit recursive slowdown.
+
+use std::fmt::Debug;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::mem;
+use lazy::Delayed;
+use deque::Deque;
+use ptr::Shareable;
+use traits;
+
+// Note:
+// In the book, the middle of a catenable deque of T is a catenable deque of deques of T. Such a type
+// (polymorphic recursion) cannot be used in Rust since it would need infinitely many instances of
+// generic functions. Instead, every level uses the same element type which is either an element of
+// the top level or a deque of elements of the next level.
+//
+// A new middle suspension evaluates the previous one. If that were still unforced, every append would
+// nest one more suspension and forcing or dropping the outermost one after many appends would recurse
+// into all of them. So suspensions are forced before new ones capture them. Since captured suspensions
+// are always forced, forcing one only goes down through the levels of the deque.
+
+fn dappend_l<E: Clone + Debug>(d1: &Deque<E>, d2: &Deque<E>) -> Deque<E> {
+    let (mut d1, mut d2) = (d1.clone(), d2.clone());
+    while let Some((x, init)) = d1.pop_back().map(|(x, d)| (x.clone(), d)) {
+        d2 = d2.enq_front(x);
+        d1 = init;
+    }
+    d2
+}
+
+fn dappend_r<E: Clone + Debug>(d1: &Deque<E>, d2: &Deque<E>) -> Deque<E> {
+    let (mut d1, mut d2) = (d1.clone(), d2.clone());
+    while let Some((x, tail)) = d2.pop_front().map(|(x, d)| (x.clone(), d)) {
+        d1 = d1.enq_back(x);
+        d2 = tail;
+    }
+    d1
+}
+
+#[derive(Clone, Debug)]
+enum Elem<T: Clone + Debug> {
+    Leaf(T),
+    Group(Deque<Elem<T>>),
+}
+
+impl<T> Elem<T>
+where T: Clone + Debug {
+    fn leaf(&self) -> &T {
+        match *self {
+            Elem::Leaf(ref x) => x,
+            Elem::Group(_) => unreachable!("elements at the top level are leaves"),
+        }
+    }
+
+    fn group(&self) -> &Deque<Self> {
+        match *self {
+            Elem::Group(ref d) => d,
+            Elem::Leaf(_) => unreachable!("elements in the middle are groups"),
+        }
+    }
+}
+
+// Invariants: f and r of Deep contain two or more elements
+#[derive(Clone, Debug)]
+enum SimpleCat<'a, T: 'a + Clone + Debug + Shareable> {
+    Shallow(Deque<Elem<T>>),
+    Deep(Deque<Elem<T>>, Delayed<'a, SimpleCat<'a, T>>, Deque<Elem<T>>),
+}
+
+use self::SimpleCat::{Shallow, Deep};
+
+impl<'a, T> SimpleCat<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn empty() -> Self {
+        Shallow(Deque::empty())
+    }
+
+    fn is_empty(&self) -> bool {
+        match *self {
+            Shallow(ref d) => d.is_empty(),
+            Deep(..) => false,
+        }
+    }
+
+    fn too_small(d: &Deque<Elem<T>>) -> bool {
+        d.len() < 2
+    }
+
+    fn cons(&self, x: Elem<T>) -> Self {
+        match *self {
+            Shallow(ref d) => Shallow(d.enq_front(x)),
+            Deep(ref f, ref m, ref r) => Deep(f.enq_front(x), m.clone(), r.clone()),
+        }
+    }
+
+    fn snoc(&self, x: Elem<T>) -> Self {
+        match *self {
+            Shallow(ref d) => Shallow(d.enq_back(x)),
+            Deep(ref f, ref m, ref r) => Deep(f.clone(), m.clone(), r.enq_back(x)),
+        }
+    }
+
+    fn head(&self) -> Option<&Elem<T>> {
+        match *self {
+            Shallow(ref d) => d.try_front(),
+            Deep(ref f, _, _) => f.try_front(),
+        }
+    }
+
+    fn last(&self) -> Option<&Elem<T>> {
+        match *self {
+            Shallow(ref d) => d.try_back(),
+            Deep(_, _, ref r) => r.try_back(),
+        }
+    }
+
+    // The deque must not be empty
+    fn tail(&self) -> Self {
+        match *self {
+            Shallow(ref d) => Shallow(d.deq_front()),
+            Deep(ref f, ref m, ref r) => {
+                let f = f.deq_front();
+                if !SimpleCat::too_small(&f) {
+                    return Deep(f, m.clone(), r.clone());
+                }
+                let mm = m.eval();
+                match mm.head() {
+                    None => Shallow(dappend_l(&f, r)),
+                    Some(d) => {
+                        let m = m.clone();
+                        Deep(dappend_l(&f, d.group()), lazily!{ m.eval().tail() }, r.clone())
+                    },
+                }
+            },
+        }
+    }
+
+    // The deque must not be empty
+    fn init(&self) -> Self {
+        match *self {
+            Shallow(ref d) => Shallow(d.deq_back()),
+            Deep(ref f, ref m, ref r) => {
+                let r = r.deq_back();
+                if !SimpleCat::too_small(&r) {
+                    return Deep(f.clone(), m.clone(), r);
+                }
+                let mm = m.eval();
+                match mm.last() {
+                    None => Shallow(dappend_r(f, &r)),
+                    Some(d) => {
+                        let m = m.clone();
+                        Deep(f.clone(), lazily!{ m.eval().init() }, dappend_r(d.group(), &r))
+                    },
+                }
+            },
+        }
+    }
+
+    fn append(&self, other: &Self) -> Self {
+        match (self, other) {
+            (Shallow(d1), Shallow(d2)) => {
+                if SimpleCat::too_small(d1) {
+                    Shallow(dappend_l(d1, d2))
+                } else if SimpleCat::too_small(d2) {
+                    Shallow(dappend_r(d1, d2))
+                } else {
+                    Deep(d1.clone(), Delayed::constant(SimpleCat::empty()), d2.clone())
+                }
+            },
+            (Shallow(d), Deep(f, m, r)) => {
+                if SimpleCat::too_small(d) {
+                    Deep(dappend_l(d, f), m.clone(), r.clone())
+                } else {
+                    m.force();
+                    let (m, f) = (m.clone(), f.clone());
+                    Deep(d.clone(), lazily!{ m.eval().cons(Elem::Group(f)) }, r.clone())
+                }
+            },
+            (Deep(f, m, r), Shallow(d)) => {
+                if SimpleCat::too_small(d) {
+                    Deep(f.clone(), m.clone(), dappend_r(r, d))
+                } else {
+                    m.force();
+                    let (m, r) = (m.clone(), r.clone());
+                    Deep(f.clone(), lazily!{ m.eval().snoc(Elem::Group(r)) }, d.clone())
+                }
+            },
+            (Deep(f1, m1, r1), Deep(f2, m2, r2)) => {
+                m1.force();
+                m2.force();
+                let (m1, r1, f2, m2) = (m1.clone(), r1.clone(), f2.clone(), m2.clone());
+                let m = lazily!{
+                    m1.eval().snoc(Elem::Group(r1)).append(&m2.eval().cons(Elem::Group(f2)))
+                };
+                Deep(f1.clone(), m, r2.clone())
+            },
+        }
+    }
+}
+
+// Dropping a middle recursively drops its own middle, as deep as the levels of the deque. Middles
+// owned only by the dropped deque are unlinked in a loop as `CatList` does.
+impl<'a, T> Drop for SimpleCat<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn drop(&mut self) {
+        let mut next = match *self {
+            Deep(_, ref mut m, _) => mem::replace(m, Delayed::constant(SimpleCat::empty())).force_unique(),
+            Shallow(_) => return,
+        };
+        while let Some(Deep(_, ref mut m, _)) = next {
+            next = mem::replace(m, Delayed::constant(SimpleCat::empty())).force_unique();
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SimpleCatDeque<'a, T: 'a + Clone + Debug + Shareable> {
+    cat: SimpleCat<'a, T>,
+    size: usize,
+}
+
+impl<'a, T> SimpleCatDeque<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    pub fn empty() -> Self {
+        SimpleCatDeque{cat: SimpleCat::empty(), size: 0}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cat.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn enq_front(&self, x: T) -> Self {
+        SimpleCatDeque{cat: self.cat.cons(Elem::Leaf(x)), size: self.size + 1}
+    }
+
+    pub fn enq_back(&self, x: T) -> Self {
+        SimpleCatDeque{cat: self.cat.snoc(Elem::Leaf(x)), size: self.size + 1}
+    }
+
+    pub fn try_front(&self) -> Option<&T> {
+        self.cat.head().map(Elem::leaf)
+    }
+
+    pub fn try_back(&self) -> Option<&T> {
+        self.cat.last().map(Elem::leaf)
+    }
+
+    pub fn try_deq_front(&self) -> Option<Self> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(SimpleCatDeque{cat: self.cat.tail(), size: self.size - 1})
+    }
+
+    pub fn try_deq_back(&self) -> Option<Self> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(SimpleCatDeque{cat: self.cat.init(), size: self.size - 1})
+    }
+
+    pub fn front(&self) -> &T {
+        self.try_front().expect("Deque is empty!")
+    }
+
+    pub fn back(&self) -> &T {
+        self.try_back().expect("Deque is empty!")
+    }
+
+    pub fn deq_front(&self) -> Self {
+        self.try_deq_front().expect("Deque is empty!")
+    }
+
+    pub fn deq_back(&self) -> Self {
+        self.try_deq_back().expect("Deque is empty!")
+    }
+
+    pub fn pop_front(&self) -> Option<(&T, Self)> {
+        self.try_front().and_then(|x| self.try_deq_front().map(|d| (x, d)))
+    }
+
+    pub fn pop_back(&self) -> Option<(&T, Self)> {
+        self.try_back().and_then(|x| self.try_deq_back().map(|d| (x, d)))
+    }
+
+    pub fn append(&self, other: &Self) -> Self {
+        SimpleCatDeque{cat: self.cat.append(&other.cat), size: self.size + other.size}
+    }
+
+    // Iterates elements from front to back. Each step dequeues from the iterator's own version.
+    pub fn iter(&self) -> Iter<T, Self> {
+        Iter{cur: self.clone(), elem: PhantomData}
+    }
+}
+
+impl<'a, T> FromIterator<T> for SimpleCatDeque<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter().fold(SimpleCatDeque::empty(), |d, x| d.enq_back(x))
+    }
+}
+
+impl<'a, T> traits::Deque<T> for SimpleCatDeque<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn empty() -> Self {
+        SimpleCatDeque::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn enq_front(&self, x: T) -> Self {
+        self.enq_front(x)
+    }
+
+    fn enq_back(&self, x: T) -> Self {
+        self.enq_back(x)
+    }
+
+    fn try_front(&self) -> Option<&T> {
+        self.try_front()
+    }
+
+    fn try_back(&self) -> Option<&T> {
+        self.try_back()
+    }
+
+    fn try_deq_front(&self) -> Option<Self> {
+        self.try_deq_front()
+    }
+
+    fn try_deq_back(&self) -> Option<Self> {
+        self.try_deq_back()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum CmpdElem<'a, T: 'a + Clone + Debug + Shareable> {
+    Leaf(T),
+    Simple(Deque<CmpdElem<'a, T>>),
+    Cmpd(Deque<CmpdElem<'a, T>>, Delayed<'a, Cat<'a, T>>, Deque<CmpdElem<'a, T>>),
+}
+
+impl<'a, T> CmpdElem<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn leaf(&self) -> &T {
+        match *self {
+            CmpdElem::Leaf(ref x) => x,
+            _ => unreachable!("elements at the top level are leaves"),
+        }
+    }
+}
+
+type CmpdDeque<'a, T> = Deque<CmpdElem<'a, T>>;
+
+// Invariants: f and r of Deep contain three or more elements and m contains two elements exactly.
+// Deques in compound elements contain two or more elements.
+#[derive(Clone, Debug)]
+enum Cat<'a, T: 'a + Clone + Debug + Shareable> {
+    Shallow(Deque<CmpdElem<'a, T>>),
+    Deep {
+        f: Deque<CmpdElem<'a, T>>,
+        a: Delayed<'a, Cat<'a, T>>,
+        m: Deque<CmpdElem<'a, T>>,
+        b: Delayed<'a, Cat<'a, T>>,
+        r: Deque<CmpdElem<'a, T>>,
+    },
+}
+
+impl<'a, T> Cat<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn empty() -> Self {
+        Cat::Shallow(Deque::empty())
+    }
+
+    fn is_empty(&self) -> bool {
+        match *self {
+            Cat::Shallow(ref d) => d.is_empty(),
+            Cat::Deep{..} => false,
+        }
+    }
+
+    fn suspended_empty() -> Delayed<'a, Self> {
+        Delayed::constant(Cat::empty())
+    }
+
+    // Moves the last element of f and the first element of r to a new middle deque
+    fn share(f: &CmpdDeque<'a, T>, r: &CmpdDeque<'a, T>) -> (CmpdDeque<'a, T>, CmpdDeque<'a, T>, CmpdDeque<'a, T>) {
+        let m = Deque::empty().enq_front(r.front().clone()).enq_front(f.back().clone());
+        (f.deq_back(), m, r.deq_front())
+    }
+
+    fn cons(&self, x: CmpdElem<'a, T>) -> Self {
+        match *self {
+            Cat::Shallow(ref d) => Cat::Shallow(d.enq_front(x)),
+            Cat::Deep{ref f, ref a, ref m, ref b, ref r} => {
+                Cat::Deep{f: f.enq_front(x), a: a.clone(), m: m.clone(), b: b.clone(), r: r.clone()}
+            },
+        }
+    }
+
+    fn snoc(&self, x: CmpdElem<'a, T>) -> Self {
+        match *self {
+            Cat::Shallow(ref d) => Cat::Shallow(d.enq_back(x)),
+            Cat::Deep{ref f, ref a, ref m, ref b, ref r} => {
+                Cat::Deep{f: f.clone(), a: a.clone(), m: m.clone(), b: b.clone(), r: r.enq_back(x)}
+            },
+        }
+    }
+
+    fn head(&self) -> Option<&CmpdElem<'a, T>> {
+        match *self {
+            Cat::Shallow(ref d) => d.try_front(),
+            Cat::Deep{ref f, ..} => f.try_front(),
+        }
+    }
+
+    fn last(&self) -> Option<&CmpdElem<'a, T>> {
+        match *self {
+            Cat::Shallow(ref d) => d.try_back(),
+            Cat::Deep{ref r, ..} => r.try_back(),
+        }
+    }
+
+    // The deque must not be empty
+    fn replace_head(&self, x: CmpdElem<'a, T>) -> Self {
+        match *self {
+            Cat::Shallow(ref d) => Cat::Shallow(d.deq_front().enq_front(x)),
+            Cat::Deep{ref f, ref a, ref m, ref b, ref r} => Cat::Deep {
+                f: f.deq_front().enq_front(x),
+                a: a.clone(),
+                m: m.clone(),
+                b: b.clone(),
+                r: r.clone(),
+            },
+        }
+    }
+
+    // The deque must not be empty
+    fn replace_last(&self, x: CmpdElem<'a, T>) -> Self {
+        match *self {
+            Cat::Shallow(ref d) => Cat::Shallow(d.deq_back().enq_back(x)),
+            Cat::Deep{ref f, ref a, ref m, ref b, ref r} => Cat::Deep {
+                f: f.clone(),
+                a: a.clone(),
+                m: m.clone(),
+                b: b.clone(),
+                r: r.deq_back().enq_back(x),
+            },
+        }
+    }
+
+    // The deque must not be empty
+    fn tail(&self) -> Self {
+        let (f, a, m, b, r) = match *self {
+            Cat::Shallow(ref d) => return Cat::Shallow(d.deq_front()),
+            Cat::Deep{ref f, ref a, ref m, ref b, ref r} => (f, a, m, b, r),
+        };
+        if f.len() > 3 {
+            return Cat::Deep{f: f.deq_front(), a: a.clone(), m: m.clone(), b: b.clone(), r: r.clone()};
+        }
+        let f = f.deq_front();
+        let aa = a.eval();
+        if let Some(x) = aa.head() {
+            let a = a.clone();
+            return match *x {
+                CmpdElem::Simple(ref d) => Cat::Deep {
+                    f: dappend_l(&f, d),
+                    a: lazily!{ a.eval().tail() },
+                    m: m.clone(),
+                    b: b.clone(),
+                    r: r.clone(),
+                },
+                CmpdElem::Cmpd(ref f2, ref c2, ref r2) => {
+                    c2.force();
+                    let (c2, r2) = (c2.clone(), r2.clone());
+                    Cat::Deep {
+                        f: dappend_l(&f, f2),
+                        a: lazily!{ c2.eval().append(&a.eval().replace_head(CmpdElem::Simple(r2))) },
+                        m: m.clone(),
+                        b: b.clone(),
+                        r: r.clone(),
+                    }
+                },
+                CmpdElem::Leaf(_) => unreachable!("elements in the middle are not leaves"),
+            };
+        }
+        let bb = b.eval();
+        if let Some(x) = bb.head() {
+            let b = b.clone();
+            return match *x {
+                CmpdElem::Simple(ref d) => Cat::Deep {
+                    f: dappend_l(&f, m),
+                    a: Cat::suspended_empty(),
+                    m: d.clone(),
+                    b: lazily!{ b.eval().tail() },
+                    r: r.clone(),
+                },
+                CmpdElem::Cmpd(ref f2, ref c2, ref r2) => {
+                    c2.force();
+                    let (f2, c2) = (f2.clone(), c2.clone());
+                    Cat::Deep {
+                        f: dappend_l(&f, m),
+                        a: lazily!{ c2.eval().cons(CmpdElem::Simple(f2)) },
+                        m: r2.clone(),
+                        b: lazily!{ b.eval().tail() },
+                        r: r.clone(),
+                    }
+                },
+                CmpdElem::Leaf(_) => unreachable!("elements in the middle are not leaves"),
+            };
+        }
+        Cat::Shallow(dappend_l(&f, m)).append(&Cat::Shallow(r.clone()))
+    }
+
+    // The deque must not be empty
+    fn init(&self) -> Self {
+        let (f, a, m, b, r) = match *self {
+            Cat::Shallow(ref d) => return Cat::Shallow(d.deq_back()),
+            Cat::Deep{ref f, ref a, ref m, ref b, ref r} => (f, a, m, b, r),
+        };
+        if r.len() > 3 {
+            return Cat::Deep{f: f.clone(), a: a.clone(), m: m.clone(), b: b.clone(), r: r.deq_back()};
+        }
+        let r = r.deq_back();
+        let bb = b.eval();
+        if let Some(x) = bb.last() {
+            let b = b.clone();
+            return match *x {
+                CmpdElem::Simple(ref d) => Cat::Deep {
+                    f: f.clone(),
+                    a: a.clone(),
+                    m: m.clone(),
+                    b: lazily!{ b.eval().init() },
+                    r: dappend_r(d, &r),
+                },
+                CmpdElem::Cmpd(ref f2, ref c2, ref r2) => {
+                    c2.force();
+                    let (f2, c2) = (f2.clone(), c2.clone());
+                    Cat::Deep {
+                        f: f.clone(),
+                        a: a.clone(),
+                        m: m.clone(),
+                        b: lazily!{ b.eval().replace_last(CmpdElem::Simple(f2)).append(c2.eval()) },
+                        r: dappend_r(r2, &r),
+                    }
+                },
+                CmpdElem::Leaf(_) => unreachable!("elements in the middle are not leaves"),
+            };
+        }
+        let aa = a.eval();
+        if let Some(x) = aa.last() {
+            let a = a.clone();
+            return match *x {
+                CmpdElem::Simple(ref d) => Cat::Deep {
+                    f: f.clone(),
+                    a: lazily!{ a.eval().init() },
+                    m: d.clone(),
+                    b: Cat::suspended_empty(),
+                    r: dappend_r(m, &r),
+                },
+                CmpdElem::Cmpd(ref f2, ref c2, ref r2) => {
+                    c2.force();
+                    let (c2, r2) = (c2.clone(), r2.clone());
+                    Cat::Deep {
+                        f: f.clone(),
+                        a: lazily!{ a.eval().init() },
+                        m: f2.clone(),
+                        b: lazily!{ c2.eval().snoc(CmpdElem::Simple(r2)) },
+                        r: dappend_r(m, &r),
+                    }
+                },
+                CmpdElem::Leaf(_) => unreachable!("elements in the middle are not leaves"),
+            };
+        }
+        Cat::Shallow(f.clone()).append(&Cat::Shallow(dappend_r(m, &r)))
+    }
+
+    fn append(&self, other: &Self) -> Self {
+        match (self, other) {
+            (Cat::Shallow(d1), Cat::Shallow(d2)) => {
+                if d1.len() < 4 {
+                    Cat::Shallow(dappend_l(d1, d2))
+                } else if d2.len() < 4 {
+                    Cat::Shallow(dappend_r(d1, d2))
+                } else {
+                    let (f, m, r) = Cat::share(d1, d2);
+                    Cat::Deep{f, a: Cat::suspended_empty(), m, b: Cat::suspended_empty(), r}
+                }
+            },
+            (Cat::Shallow(d), Cat::Deep{f, a, m, b, r}) => {
+                if d.len() < 4 {
+                    Cat::Deep{f: dappend_l(d, f), a: a.clone(), m: m.clone(), b: b.clone(), r: r.clone()}
+                } else {
+                    a.force();
+                    let (a, f) = (a.clone(), f.clone());
+                    Cat::Deep {
+                        f: d.clone(),
+                        a: lazily!{ a.eval().cons(CmpdElem::Simple(f)) },
+                        m: m.clone(),
+                        b: b.clone(),
+                        r: r.clone(),
+                    }
+                }
+            },
+            (Cat::Deep{f, a, m, b, r}, Cat::Shallow(d)) => {
+                if d.len() < 4 {
+                    Cat::Deep{f: f.clone(), a: a.clone(), m: m.clone(), b: b.clone(), r: dappend_r(r, d)}
+                } else {
+                    b.force();
+                    let (b, r) = (b.clone(), r.clone());
+                    Cat::Deep {
+                        f: f.clone(),
+                        a: a.clone(),
+                        m: m.clone(),
+                        b: lazily!{ b.eval().snoc(CmpdElem::Simple(r)) },
+                        r: d.clone(),
+                    }
+                }
+            },
+            (Cat::Deep{f: f1, a: a1, m: m1, b: b1, r: r1}, Cat::Deep{f: f2, a: a2, m: m2, b: b2, r: r2}) => {
+                let (r1, m, f2) = Cat::share(r1, f2);
+                a1.force();
+                b2.force();
+                let (a1, m1, b1) = (a1.clone(), m1.clone(), b1.clone());
+                let (a2, m2, b2) = (a2.clone(), m2.clone(), b2.clone());
+                Cat::Deep {
+                    f: f1.clone(),
+                    a: lazily!{ a1.eval().snoc(CmpdElem::Cmpd(m1, b1, r1)) },
+                    m,
+                    b: lazily!{ b2.eval().cons(CmpdElem::Cmpd(f2, a2, m2)) },
+                    r: r2.clone(),
+                }
+            },
+        }
+    }
+}
+
+// Same as `SimpleCat` but each deque has two middles
+impl<'a, T> Drop for Cat<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn drop(&mut self) {
+        fn take_middles<'a, T>(c: &mut Cat<'a, T>, middles: &mut Vec<Delayed<'a, Cat<'a, T>>>)
+        where T: 'a + Clone + Debug + Shareable {
+            if let Cat::Deep{ref mut a, ref mut b, ..} = *c {
+                middles.push(mem::replace(a, Cat::suspended_empty()));
+                middles.push(mem::replace(b, Cat::suspended_empty()));
+            }
+        }
+
+        let mut middles = vec![];
+        take_middles(self, &mut middles);
+        while let Some(mut m) = middles.pop() {
+            if let Some(mut c) = m.force_unique() {
+                take_middles(&mut c, &mut middles);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CatDeque<'a, T: 'a + Clone + Debug + Shareable> {
+    cat: Cat<'a, T>,
+    size: usize,
+}
+
+impl<'a, T> CatDeque<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    pub fn empty() -> Self {
+        CatDeque{cat: Cat::empty(), size: 0}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cat.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn enq_front(&self, x: T) -> Self {
+        CatDeque{cat: self.cat.cons(CmpdElem::Leaf(x)), size: self.size + 1}
+    }
+
+    pub fn enq_back(&self, x: T) -> Self {
+        CatDeque{cat: self.cat.snoc(CmpdElem::Leaf(x)), size: self.size + 1}
+    }
+
+    pub fn try_front(&self) -> Option<&T> {
+        self.cat.head().map(CmpdElem::leaf)
+    }
+
+    pub fn try_back(&self) -> Option<&T> {
+        self.cat.last().map(CmpdElem::leaf)
+    }
+
+    pub fn try_deq_front(&self) -> Option<Self> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(CatDeque{cat: self.cat.tail(), size: self.size - 1})
+    }
+
+    pub fn try_deq_back(&self) -> Option<Self> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(CatDeque{cat: self.cat.init(), size: self.size - 1})
+    }
+
+    pub fn front(&self) -> &T {
+        self.try_front().expect("Deque is empty!")
+    }
+
+    pub fn back(&self) -> &T {
+        self.try_back().expect("Deque is empty!")
+    }
+
+    pub fn deq_front(&self) -> Self {
+        self.try_deq_front().expect("Deque is empty!")
+    }
+
+    pub fn deq_back(&self) -> Self {
+        self.try_deq_back().expect("Deque is empty!")
+    }
+
+    pub fn pop_front(&self) -> Option<(&T, Self)> {
+        self.try_front().and_then(|x| self.try_deq_front().map(|d| (x, d)))
+    }
+
+    pub fn pop_back(&self) -> Option<(&T, Self)> {
+        self.try_back().and_then(|x| self.try_deq_back().map(|d| (x, d)))
+    }
+
+    pub fn append(&self, other: &Self) -> Self {
+        CatDeque{cat: self.cat.append(&other.cat), size: self.size + other.size}
+    }
+
+    // Iterates elements from front to back. Each step dequeues from the iterator's own version.
+    pub fn iter(&self) -> Iter<T, Self> {
+        Iter{cur: self.clone(), elem: PhantomData}
+    }
+}
+
+impl<'a, T> FromIterator<T> for CatDeque<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter().fold(CatDeque::empty(), |d, x| d.enq_back(x))
+    }
+}
+
+impl<'a, T> traits::Deque<T> for CatDeque<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn empty() -> Self {
+        CatDeque::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn enq_front(&self, x: T) -> Self {
+        self.enq_front(x)
+    }
+
+    fn enq_back(&self, x: T) -> Self {
+        self.enq_back(x)
+    }
+
+    fn try_front(&self) -> Option<&T> {
+        self.try_front()
+    }
+
+    fn try_back(&self) -> Option<&T> {
+        self.try_back()
+    }
+
+    fn try_deq_front(&self) -> Option<Self> {
+        self.try_deq_front()
+    }
+
+    fn try_deq_back(&self) -> Option<Self> {
+        self.try_deq_back()
+    }
+}
+
+// Shared by both deques in this module
+pub struct Iter<T, D> {
+    cur: D,
+    elem: PhantomData<T>,
+}
+
+impl<T, D> Iterator for Iter<T, D>
+where T: Clone, D: traits::Deque<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (x, d) = match self.cur.pop_front() {
+            Some((x, d)) => (x.clone(), d),
+            None => return None,
+        };
+        self.cur = d;
+        Some(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_simple_enq_deq() {
+        let d = SimpleCatDeque::<i32>::empty();
+        assert!(d.is_empty());
+        assert!(d.try_front().is_none());
+        assert!(d.try_deq_back().is_none());
+        let d = d.enq_back(2).enq_front(1).enq_back(3);
+        assert_eq!(d.len(), 3);
+        assert_eq!(d.front(), &1);
+        assert_eq!(d.back(), &3);
+        let d = d.deq_front().deq_front();
+        assert_eq!(d.front(), &3);
+        assert_eq!(d.back(), &3);
+        assert!(d.deq_back().is_empty());
+    }
+
+    #[test]
+    fn test_enq_deq() {
+        let d = CatDeque::<i32>::empty();
+        assert!(d.is_empty());
+        assert!(d.try_front().is_none());
+        assert!(d.try_deq_back().is_none());
+        let d = d.enq_back(2).enq_front(1).enq_back(3);
+        assert_eq!(d.len(), 3);
+        assert_eq!(d.front(), &1);
+        assert_eq!(d.back(), &3);
+        let d = d.deq_front().deq_front();
+        assert_eq!(d.front(), &3);
+        assert_eq!(d.back(), &3);
+        assert!(d.deq_back().is_empty());
+    }
+
+    #[test]
+    fn test_append() {
+        let (s1, s2) = ((0..10).collect::<SimpleCatDeque<_>>(), (10..20).collect::<SimpleCatDeque<_>>());
+        let s = s1.append(&s2);
+        assert_eq!(s.len(), 20);
+        assert_eq!(s.iter().collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+        assert_eq!(s1.iter().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        let (d1, d2) = ((0..10).collect::<CatDeque<_>>(), (10..20).collect::<CatDeque<_>>());
+        let d = d1.append(&d2);
+        assert_eq!(d.len(), 20);
+        assert_eq!(d.iter().collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+        assert_eq!(d2.iter().collect::<Vec<_>>(), (10..20).collect::<Vec<_>>());
+        assert!(d.append(&CatDeque::empty()).iter().eq(0..20));
+        assert!(CatDeque::empty().append(&d).iter().eq(0..20));
+    }
+
+    // Applies the same random-ish operations including appends of various sizes to both deques
+    macro_rules! same_as_vec_deque {
+        ($ty:ident) => {{
+            let mut d = $ty::empty();
+            let mut v = VecDeque::new();
+            // Small old versions are kept and appended again to check persistence
+            let mut olds = vec![];
+            for i in 0..3000 {
+                match i * 7 % 13 {
+                    0..=2 => {
+                        d = d.enq_front(i);
+                        v.push_front(i);
+                    },
+                    3..=5 => {
+                        d = d.enq_back(i);
+                        v.push_back(i);
+                    },
+                    6 | 7 => {
+                        d = d.try_deq_front().unwrap_or(d);
+                        v.pop_front();
+                    },
+                    8 | 9 => {
+                        d = d.try_deq_back().unwrap_or(d);
+                        v.pop_back();
+                    },
+                    10 => {
+                        let n = i % 11;
+                        let other = (0..n).map(|j| i * 100 + j).collect::<$ty<_>>();
+                        d = d.append(&other);
+                        v.extend((0..n).map(|j| i * 100 + j));
+                    },
+                    11 if !olds.is_empty() => {
+                        let (old, w): &($ty<_>, VecDeque<_>) = &olds[(i as usize * 31) % olds.len()];
+                        d = old.append(&d);
+                        let mut w = w.clone();
+                        w.extend(v.iter().cloned());
+                        v = w;
+                    },
+                    _ if v.len() < 50 => olds.push((d.clone(), v.clone())),
+                    _ => (),
+                }
+                assert_eq!(d.len(), v.len());
+                assert_eq!(d.try_front(), v.front());
+                assert_eq!(d.try_back(), v.back());
+            }
+            assert!(d.iter().eq(v.iter().cloned()));
+            for (old, w) in olds {
+                assert!(old.iter().eq(w.into_iter()));
+            }
+        }};
+    }
+
+    #[test]
+    fn test_same_as_vec_deque() {
+        same_as_vec_deque!(SimpleCatDeque);
+        same_as_vec_deque!(CatDeque);
+    }
+
+    // Appending small deques one by one is the typical use of catenable deques (e.g. a text buffer)
+    macro_rules! many_appends {
+        ($ty:ident) => {{
+            let n = 100_000;
+            let d = (0..n).fold($ty::empty(), |d, i| d.append(&(i * 5..i * 5 + 5).collect()));
+            assert_eq!(d.len(), n as usize * 5);
+            let mut front = d.clone();
+            for i in 0..n * 5 / 2 {
+                assert_eq!(front.front(), &i);
+                front = front.deq_front();
+            }
+            let mut back = front.clone();
+            for i in (n * 5 / 2..n * 5).rev() {
+                assert_eq!(back.back(), &i);
+                back = back.deq_back();
+            }
+            assert!(back.is_empty());
+            // Drop versions before and after forcing their middles
+            drop(front);
+            drop(d);
+            let d = (0..n).fold($ty::empty(), |d, i| d.append(&(i * 5..i * 5 + 5).collect()));
+            drop(d);
+        }};
+    }
+
+    #[test]
+    fn test_many_appends() {
+        many_appends!(SimpleCatDeque);
+        many_appends!(CatDeque);
+    }
+}
@@ -0,0 +1,252 @@
+// Section 10.2.1
+//
+// Catenable list.
+// Amortized O(1) cons, snoc, append, head and tail even if old versions are used persistently
+
+use std::fmt::Debug;
+use std::iter::FromIterator;
+use std::mem;
+use lazy::Delayed;
+use bankersqueue::BankersQueue;
+use ptr::Shareable;
+use traits;
+
+// Note:
+// A non-empty list is a tree whose root has the first element. Its children are suspended lists
+// stored in a queue from left to right. Appending a list pushes it to the queue of the root, and
+// removing the root links its children lazily from right to left. The queue must be a persistent one
+// (banker's queue here) so that the amortized bounds are kept under persistence.
+#[derive(Clone, Debug)]
+enum Node<'a, T: 'a + Clone + Debug + Shareable> {
+    Empty,
+    Cat(T, BankersQueue<'a, Delayed<'a, CatList<'a, T>>>),
+}
+
+#[derive(Clone, Debug)]
+pub struct CatList<'a, T: 'a + Clone + Debug + Shareable>(Node<'a, T>);
+
+impl<'a, T> CatList<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    pub fn empty() -> Self {
+        CatList(Node::Empty)
+    }
+
+    pub fn one(x: T) -> Self {
+        CatList(Node::Cat(x, BankersQueue::empty()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(self.0, Node::Empty)
+    }
+
+    fn link(&self, s: Delayed<'a, Self>) -> Self {
+        match self.0 {
+            Node::Cat(ref x, ref q) => CatList(Node::Cat(x.clone(), q.enq(s))),
+            Node::Empty => unreachable!("cannot link to empty list"),
+        }
+    }
+
+    fn link_all(q: &BankersQueue<'a, Delayed<'a, Self>>) -> Self {
+        let (t, q) = q.pop().expect("queue of children is not empty");
+        let t = t.eval();
+        if q.is_empty() {
+            t.clone()
+        } else {
+            t.link(lazily!{
+                CatList::link_all(&q)
+            })
+        }
+    }
+
+    pub fn append(&self, other: &Self) -> Self {
+        if other.is_empty() {
+            self.clone()
+        } else if self.is_empty() {
+            other.clone()
+        } else {
+            self.link(Delayed::constant(other.clone()))
+        }
+    }
+
+    pub fn cons(&self, x: T) -> Self {
+        CatList::one(x).append(self)
+    }
+
+    pub fn snoc(&self, x: T) -> Self {
+        self.append(&CatList::one(x))
+    }
+
+    pub fn uncons(&self) -> Option<(&T, Self)> {
+        match self.0 {
+            Node::Empty => None,
+            Node::Cat(ref x, ref q) if q.is_empty() => Some((x, CatList::empty())),
+            Node::Cat(ref x, ref q) => Some((x, CatList::link_all(q))),
+        }
+    }
+
+    pub fn try_head(&self) -> Option<&T> {
+        match self.0 {
+            Node::Empty => None,
+            Node::Cat(ref x, _) => Some(x),
+        }
+    }
+
+    pub fn try_tail(&self) -> Option<Self> {
+        self.uncons().map(|(_, xs)| xs)
+    }
+
+    pub fn head(&self) -> &T {
+        self.try_head().expect("List is empty!")
+    }
+
+    pub fn tail(&self) -> Self {
+        self.try_tail().expect("List is empty!")
+    }
+
+    // Iterates elements from head to last. Each step takes tail of the iterator's own version.
+    pub fn iter(&self) -> Iter<'a, T> {
+        Iter{cur: self.clone()}
+    }
+}
+
+// `cons` only makes a tree as deep as the list is long, and dropping it recursively overflows the
+// stack. Children owned only by the dropped tree are unlinked in a loop as `List` does.
+impl<'a, T> Drop for CatList<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn drop(&mut self) {
+        let mut children = match mem::replace(&mut self.0, Node::Empty) {
+            Node::Cat(_, q) => q.into_unique_elems(),
+            Node::Empty => return,
+        };
+        while let Some(mut c) = children.pop() {
+            if let Some(mut l) = c.force_unique() {
+                if let Node::Cat(_, q) = mem::replace(&mut l.0, Node::Empty) {
+                    children.extend(q.into_unique_elems());
+                }
+            }
+        }
+    }
+}
+
+pub struct Iter<'a, T: 'a + Clone + Debug + Shareable> {
+    cur: CatList<'a, T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (x, xs) = match self.cur.uncons() {
+            Some((x, xs)) => (x.clone(), xs),
+            None => return None,
+        };
+        self.cur = xs;
+        Some(x)
+    }
+}
+
+impl<'a, T> FromIterator<T> for CatList<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter().fold(CatList::empty(), |l, x| l.snoc(x))
+    }
+}
+
+impl<'a, T> traits::Stack<T> for CatList<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn empty() -> Self {
+        CatList::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn cons(&self, x: T) -> Self {
+        self.cons(x)
+    }
+
+    fn try_head(&self) -> Option<&T> {
+        self.try_head()
+    }
+
+    fn try_tail(&self) -> Option<Self> {
+        self.try_tail()
+    }
+
+    fn uncons(&self) -> Option<(&T, Self)> {
+        self.uncons()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_vec(l: &CatList<i32>) -> Vec<i32> {
+        l.iter().collect()
+    }
+
+    #[test]
+    fn test_cons_snoc() {
+        let l = CatList::empty();
+        assert!(l.is_empty());
+        assert!(l.uncons().is_none());
+        let l = l.cons(2).snoc(3).cons(1).snoc(4);
+        assert!(!l.is_empty());
+        assert_eq!(l.head(), &1);
+        assert_eq!(l.tail().head(), &2);
+        assert_eq!(to_vec(&l), vec![1, 2, 3, 4]);
+        assert!(l.tail().tail().tail().tail().is_empty());
+    }
+
+    #[test]
+    fn test_append() {
+        let l1 = (0..10).collect::<CatList<_>>();
+        let l2 = (10..20).collect::<CatList<_>>();
+        let l = l1.append(&l2);
+        assert_eq!(to_vec(&l), (0..20).collect::<Vec<_>>());
+        assert_eq!(to_vec(&l.append(&CatList::empty())), (0..20).collect::<Vec<_>>());
+        assert_eq!(to_vec(&CatList::empty().append(&l)), (0..20).collect::<Vec<_>>());
+        // Operands are not changed
+        assert_eq!(to_vec(&l1), (0..10).collect::<Vec<_>>());
+        assert_eq!(to_vec(&l2), (10..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_many_appends() {
+        // Appends many small lists like aggregating logs
+        let mut l = CatList::empty();
+        let mut expected = vec![];
+        for i in 0..300 {
+            let chunk = (0..i % 7).map(|j| i * 10 + j).collect::<CatList<_>>();
+            expected.extend(chunk.iter());
+            l = l.append(&chunk);
+        }
+        assert_eq!(to_vec(&l), expected);
+        // Persistent: taking tails of the same version again gives the same result
+        let t = l.tail();
+        assert_eq!(to_vec(&t), expected[1..].to_vec());
+        assert_eq!(to_vec(&l.tail()), expected[1..].to_vec());
+    }
+
+    #[test]
+    fn test_long() {
+        let l = (0..1000).fold(CatList::empty(), |l, i| l.cons(i));
+        assert_eq!(l.iter().collect::<Vec<_>>(), (0..1000).rev().collect::<Vec<_>>());
+        let l = (0..100000).fold(CatList::empty(), |l, i| l.snoc(i));
+        assert_eq!(l.iter().count(), 100000);
+        let l = (0..1000).fold(CatList::empty(), |l, i| l.append(&(0..100).map(|j| i * 100 + j).collect()));
+        assert_eq!(l.iter().count(), 100000);
+    }
+
+    #[test]
+    fn test_drop_long() {
+        // Each `cons` nests the rest of the list one level deeper
+        let l = (0..1000000).fold(CatList::empty(), |l, i| l.cons(i));
+        assert_eq!(l.head(), &999999);
+        assert_eq!(l.tail().head(), &999998);
+        drop(l);
+    }
+}
@@ -0,0 +1,21 @@
+// Element type for tests which measure work of operations by counting how many times elements are
+// copied. Counts are kept per thread so that tests running in parallel don't interfere.
+
+use std::cell::Cell;
+
+thread_local!(static CLONES: Cell<usize> = const { Cell::new(0) });
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Counted(pub i32);
+
+impl Clone for Counted {
+    fn clone(&self) -> Self {
+        CLONES.with(|c| c.set(c.get() + 1));
+        Counted(self.0)
+    }
+}
+
+// Total number of copies made in the current thread
+pub fn clones() -> usize {
+    CLONES.with(|c| c.get())
+}
@@ -4,87 +4,209 @@
 
 use std::fmt::Debug;
 use list::{List, Node};
+use traits;
+
+pub use list::FrontBackIter as Iter;
 
 // Invariant: When it contains two or more elements, both f and r contains at least one element.
 #[derive(Clone, Debug)]
 pub struct Deque<T: Clone + Debug> {
     f: List<T>,
+    lenf: usize,
     r: List<T>,
+    lenr: usize,
 }
 
 impl<T> Deque<T>
 where T: Clone + Debug {
     pub fn empty() -> Self {
         let l = List::empty();
-        Deque{f: l.clone(), r: l}
+        Deque{f: l.clone(), lenf: 0, r: l, lenr: 0}
     }
 
     pub fn is_empty(&self) -> bool {
         self.f.is_empty() && self.r.is_empty()
     }
 
-    fn make(f: List<T>, r: List<T>) -> Self {
+    pub fn len(&self) -> usize {
+        self.lenf + self.lenr
+    }
+
+    // Splits `l` into its first `n` elements and the rest reversed
+    fn split(l: &List<T>, n: usize) -> (List<T>, List<T>) {
+        let mut rest = l;
+        for _ in 0..n {
+            rest = rest.tail();
+        }
+        (l.iter().take(n).cloned().collect(), rest.rev())
+    }
+
+    fn make(f: List<T>, lenf: usize, r: List<T>, lenr: usize) -> Self {
         // Meet Invariant: Both f and r must have one or more elements if the
-        // Deque has two or more elements.
-        match (f.is_empty(), r.is_empty()) {
-            (true, false) => Deque{f: r.tail().rev(), r: List::one(r.head().clone())},
-            (false, true) => Deque{f: List::one(f.head().clone()), r: f.tail().rev()},
-            _ => Deque{f, r},
+        // Deque has two or more elements. Elements are split in half so that
+        // dequeuing from both sides alternately doesn't reverse whole list each time.
+        if lenf == 0 && lenr > 1 {
+            let n = lenr / 2;
+            let (r, f) = Deque::split(&r, n);
+            Deque{f, lenf: lenr - n, r, lenr: n}
+        } else if lenr == 0 && lenf > 1 {
+            let n = lenf / 2;
+            let (f, r) = Deque::split(&f, n);
+            Deque{f, lenf: n, r, lenr: lenf - n}
+        } else {
+            Deque{f, lenf, r, lenr}
         }
     }
 
     pub fn enq_front(&self, x: T) -> Self {
-        Deque::make(self.f.cons(x), self.r.clone())
+        Deque::make(self.f.cons(x), self.lenf + 1, self.r.clone(), self.lenr)
     }
 
     pub fn enq_back(&self, x: T) -> Self {
-        Deque::make(self.f.clone(), self.r.cons(x))
+        Deque::make(self.f.clone(), self.lenf, self.r.cons(x), self.lenr + 1)
     }
 
-    pub fn deq_front(&self) -> Self {
-        match self.f.root() {
-            &Node::Nil => {
-                if self.r.is_empty() { panic!("Deque is empty!") }
+    pub fn try_deq_front(&self) -> Option<Self> {
+        match *self.f.root() {
+            Node::Nil => {
+                if self.r.is_empty() { return None }
                 // Only one element is in deque due to invariant
-                Deque::empty()
+                Some(Deque::empty())
             },
-            &Node::Cons(_, ref xs) => {
-                Deque::make(xs.clone(), self.r.clone())
+            Node::Cons(_, ref xs) => {
+                Some(Deque::make(xs.clone(), self.lenf - 1, self.r.clone(), self.lenr))
             },
         }
     }
 
-    pub fn deq_back(&self) -> Self {
-        match self.r.root() {
-            &Node::Nil => {
-                if self.f.is_empty() { panic!("Deque is empty!") }
+    pub fn try_deq_back(&self) -> Option<Self> {
+        match *self.r.root() {
+            Node::Nil => {
+                if self.f.is_empty() { return None }
                 // Only one element is in deque due to invariant
-                Deque::empty()
+                Some(Deque::empty())
             },
-            &Node::Cons(_, ref xs) => {
-                Deque::make(self.f.clone(), xs.clone())
+            Node::Cons(_, ref xs) => {
+                Some(Deque::make(self.f.clone(), self.lenf, xs.clone(), self.lenr - 1))
             },
         }
     }
 
-    fn peek<'a>(x: &'a List<T>, y: &'a List<T>) -> &'a T {
-        match x.root() {
-            &Node::Nil => {
-                if y.is_empty() { panic!("Deque is empty!") }
-                // Only one element is in deque due to invariant
-                y.head()
-            },
-            &Node::Cons(ref v, _) => v,
+    pub fn deq_front(&self) -> Self {
+        self.try_deq_front().expect("Deque is empty!")
+    }
+
+    pub fn deq_back(&self) -> Self {
+        self.try_deq_back().expect("Deque is empty!")
+    }
+
+    fn peek<'a>(x: &'a List<T>, y: &'a List<T>) -> Option<&'a T> {
+        match *x.root() {
+            // Only one element is in deque due to invariant
+            Node::Nil => y.try_head(),
+            Node::Cons(ref v, _) => Some(v),
         }
     }
 
-    pub fn front(&self) -> &T {
+    pub fn try_front(&self) -> Option<&T> {
         Deque::peek(&self.f, &self.r)
     }
 
-    pub fn back(&self) -> &T {
+    pub fn try_back(&self) -> Option<&T> {
         Deque::peek(&self.r, &self.f)
     }
+
+    pub fn front(&self) -> &T {
+        self.try_front().expect("Deque is empty!")
+    }
+
+    pub fn back(&self) -> &T {
+        self.try_back().expect("Deque is empty!")
+    }
+
+    pub fn pop_front(&self) -> Option<(&T, Self)> {
+        self.try_front().and_then(|x| self.try_deq_front().map(|d| (x, d)))
+    }
+
+    pub fn pop_back(&self) -> Option<(&T, Self)> {
+        self.try_back().and_then(|x| self.try_deq_back().map(|d| (x, d)))
+    }
+
+    // Iterates elements from front to back
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter::new(&self.f, &self.r)
+    }
+
+    // Iterates elements from back to front
+    pub fn iter_rev(&self) -> Iter<'_, T> {
+        Iter::new(&self.r, &self.f)
+    }
+
+    // `idx`-th element from front. O(idx) when it is in f and O(n - idx) when it is in r, so O(n) in
+    // the worst case.
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        if idx < self.lenf {
+            self.f.iter().nth(idx)
+        } else if idx < self.len() {
+            self.r.iter().nth(self.len() - 1 - idx)
+        } else {
+            None
+        }
+    }
+
+    pub fn contains(&self, x: &T) -> bool
+    where T: PartialEq {
+        self.f.iter().chain(self.r.iter()).any(|y| y == x)
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Deque<T>
+where T: Clone + Debug {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> traits::Deque<T> for Deque<T>
+where T: Clone + Debug {
+    fn empty() -> Self {
+        Deque::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn enq_front(&self, x: T) -> Self {
+        self.enq_front(x)
+    }
+
+    fn enq_back(&self, x: T) -> Self {
+        self.enq_back(x)
+    }
+
+    fn try_front(&self) -> Option<&T> {
+        self.try_front()
+    }
+
+    fn try_back(&self) -> Option<&T> {
+        self.try_back()
+    }
+
+    fn try_deq_front(&self) -> Option<Self> {
+        self.try_deq_front()
+    }
+
+    fn try_deq_back(&self) -> Option<Self> {
+        self.try_deq_back()
+    }
 }
 
 #[cfg(test)]
@@ -99,6 +221,23 @@ mod tests {
         assert!(!d.is_empty());
     }
 
+    #[test]
+    fn test_try() {
+        let d = Deque::<i32>::empty();
+        assert!(d.try_front().is_none());
+        assert!(d.try_back().is_none());
+        assert!(d.try_deq_front().is_none());
+        assert!(d.try_deq_back().is_none());
+
+        let d = d.enq_back(1).enq_back(2);
+        let (x, d) = d.pop_front().unwrap();
+        assert_eq!(x, &1);
+        let (x, d) = d.pop_back().unwrap();
+        assert_eq!(x, &2);
+        assert!(d.pop_front().is_none());
+        assert!(d.pop_back().is_none());
+    }
+
     #[test]
     fn test_front_to_back() {
         let d = Deque::empty().enq_front(1);
@@ -149,4 +288,56 @@ mod tests {
         let d = d.deq_front().deq_back().deq_front().deq_back();
         assert!(d.is_empty());
     }
+
+    #[test]
+    fn test_len() {
+        let d = Deque::empty();
+        assert_eq!(d.len(), 0);
+        let d = d.enq_front(2).enq_back(3).enq_front(1).enq_back(4);
+        assert_eq!(d.len(), 4);
+        assert_eq!(d.deq_front().len(), 3);
+        assert_eq!(d.deq_back().deq_back().deq_back().len(), 1);
+        assert_eq!(d.deq_back().deq_back().deq_back().deq_back().len(), 0);
+    }
+
+    #[test]
+    fn test_split_in_half() {
+        let mut d = Deque::empty();
+        for i in 0..10 {
+            d = d.enq_back(i);
+        }
+        // Moving elements from r to f keeps half of them in r
+        let d = d.deq_front();
+        assert_eq!((d.lenf, d.lenr), (5, 4));
+        assert_eq!(d.f.len(), d.lenf);
+        assert_eq!(d.r.len(), d.lenr);
+        assert_eq!(d.to_vec(), (1..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_iter() {
+        let d = Deque::empty().enq_front(2).enq_back(3).enq_front(1).enq_back(4);
+        assert_eq!(d.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(d.iter_rev().cloned().collect::<Vec<_>>(), vec![4, 3, 2, 1]);
+        assert_eq!(d.to_vec(), vec![1, 2, 3, 4]);
+        let mut v = vec![];
+        for x in &d {
+            v.push(*x);
+        }
+        assert_eq!(v, vec![1, 2, 3, 4]);
+        assert!(Deque::<i32>::empty().iter().next().is_none());
+        assert!(Deque::<i32>::empty().iter_rev().next().is_none());
+    }
+
+    #[test]
+    fn test_get_contains() {
+        let d = Deque::empty().enq_front(2).enq_back(3).enq_front(1).enq_back(4).deq_front().enq_front(1);
+        for i in 0..4 {
+            assert_eq!(d.get(i), Some(&(i as i32 + 1)));
+            assert!(d.contains(&(i as i32 + 1)));
+        }
+        assert_eq!(d.get(4), None);
+        assert!(!d.contains(&0));
+        assert!(!d.contains(&5));
+    }
 } // mod tests
@@ -0,0 +1,230 @@
+// Section 8.2.1
+//
+// Hood-Melville queue.
+// Worst-case O(1) operations (enqueue/dequeue) by global rebuilding. No lazy evaluation is used.
+
+use std::fmt::Debug;
+use list::List;
+use traits;
+
+// Note:
+// Rotation `f ++ reverse r` is divided into steps. The state machine reverses f and r at the same
+// time, then appends reversed f onto reversed r. `ok` is the number of elements of the reversed f
+// which are still in the queue. Elements dequeued during the rotation are invalidated by decrementing
+// it so that they are not appended.
+#[derive(Clone, Debug)]
+enum RotationState<T: Clone + Debug> {
+    Idle,
+    Reversing{ok: usize, f: List<T>, f2: List<T>, r: List<T>, r2: List<T>},
+    Appending{ok: usize, f2: List<T>, r2: List<T>},
+    Done(List<T>),
+}
+
+use self::RotationState::*;
+
+impl<T> RotationState<T>
+where T: Clone + Debug {
+    fn exec(&self) -> Self {
+        match *self {
+            Reversing{ok, ref f, ref f2, ref r, ref r2} => match (f.uncons(), r.uncons()) {
+                (Some((x, f)), Some((y, r))) => Reversing {
+                    ok: ok + 1,
+                    f: f.clone(),
+                    f2: f2.cons(x.clone()),
+                    r: r.clone(),
+                    r2: r2.cons(y.clone()),
+                },
+                (None, Some((y, r))) if r.is_empty() => Appending{ok, f2: f2.clone(), r2: r2.cons(y.clone())},
+                _ => unreachable!("rear list must be longer than front list by one"),
+            },
+            Appending{ok: 0, ref r2, ..} => Done(r2.clone()),
+            Appending{ok, ref f2, ref r2} => {
+                let (x, f2) = f2.uncons().expect("reversed front list has `ok` elements");
+                Appending{ok: ok - 1, f2: f2.clone(), r2: r2.cons(x.clone())}
+            },
+            _ => self.clone(),
+        }
+    }
+
+    fn invalidate(&self) -> Self {
+        match *self {
+            Reversing{ok, ref f, ref f2, ref r, ref r2} => Reversing {
+                ok: ok - 1,
+                f: f.clone(),
+                f2: f2.clone(),
+                r: r.clone(),
+                r2: r2.clone(),
+            },
+            Appending{ok: 0, ref r2, ..} => Done(r2.tail().clone()),
+            Appending{ok, ref f2, ref r2} => Appending{ok: ok - 1, f2: f2.clone(), r2: r2.clone()},
+            _ => self.clone(),
+        }
+    }
+}
+
+// Note:
+// Invariants: lenr <= lenf
+// When r gets longer than f, the rotation starts and it is executed two steps by every operation.
+// It completes before all elements of the old f are dequeued.
+#[derive(Clone, Debug)]
+pub struct HoodMelvilleQueue<T: Clone + Debug> {
+    lenf: usize,
+    f: List<T>,
+    state: RotationState<T>,
+    lenr: usize,
+    r: List<T>,
+}
+
+impl<T> HoodMelvilleQueue<T>
+where T: Clone + Debug {
+    pub fn empty() -> Self {
+        HoodMelvilleQueue{lenf: 0, f: List::empty(), state: Idle, lenr: 0, r: List::empty()}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lenf == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.lenf + self.lenr
+    }
+
+    fn exec2(lenf: usize, f: List<T>, state: RotationState<T>, lenr: usize, r: List<T>) -> Self {
+        match state.exec().exec() {
+            Done(f) => HoodMelvilleQueue{lenf, f, state: Idle, lenr, r},
+            state => HoodMelvilleQueue{lenf, f, state, lenr, r},
+        }
+    }
+
+    fn check(lenf: usize, f: List<T>, state: RotationState<T>, lenr: usize, r: List<T>) -> Self {
+        if lenr <= lenf {
+            HoodMelvilleQueue::exec2(lenf, f, state, lenr, r)
+        } else {
+            let state = Reversing{ok: 0, f: f.clone(), f2: List::empty(), r, r2: List::empty()};
+            HoodMelvilleQueue::exec2(lenf + lenr, f, state, 0, List::empty())
+        }
+    }
+
+    pub fn enq(&self, x: T) -> Self {
+        HoodMelvilleQueue::check(self.lenf, self.f.clone(), self.state.clone(), self.lenr + 1, self.r.cons(x))
+    }
+
+    pub fn pop(&self) -> Option<(&T, Self)> {
+        self.f.uncons().map(|(x, f)| {
+            let q = HoodMelvilleQueue::check(self.lenf - 1, f.clone(), self.state.invalidate(), self.lenr, self.r.clone());
+            (x, q)
+        })
+    }
+
+    pub fn try_top(&self) -> Option<&T> {
+        self.f.try_head()
+    }
+
+    pub fn try_deq(&self) -> Option<Self> {
+        self.pop().map(|(_, q)| q)
+    }
+
+    pub fn top(&self) -> &T {
+        self.try_top().expect("Queue is empty")
+    }
+
+    pub fn deq(&self) -> Self {
+        self.try_deq().expect("Queue is empty")
+    }
+}
+
+impl<T> traits::Queue<T> for HoodMelvilleQueue<T>
+where T: Clone + Debug {
+    fn empty() -> Self {
+        HoodMelvilleQueue::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn enq(&self, x: T) -> Self {
+        self.enq(x)
+    }
+
+    fn try_top(&self) -> Option<&T> {
+        self.try_top()
+    }
+
+    fn try_deq(&self) -> Option<Self> {
+        self.try_deq()
+    }
+
+    fn pop(&self) -> Option<(&T, Self)> {
+        self.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use counted::{Counted, clones};
+
+    #[test]
+    fn test_pop() {
+        let q = HoodMelvilleQueue::<i32>::empty();
+        assert!(q.is_empty());
+        assert!(q.pop().is_none());
+        let q = q.enq(1).enq(2).enq(3);
+        assert_eq!(q.len(), 3);
+        let (x, q) = q.pop().unwrap();
+        assert_eq!(x, &1);
+        let mut q = q.enq(4);
+        let mut v = vec![];
+        while let Some((x, rest)) = q.pop() {
+            v.push(*x);
+            q = rest;
+        }
+        assert_eq!(v, vec![2, 3, 4]);
+        assert!(q.is_empty());
+        assert_eq!(q.len(), 0);
+    }
+
+    #[test]
+    fn test_fifo() {
+        // Mixes operations so that dequeues happen while rotations are in progress
+        for period in 2..6 {
+            let mut q = HoodMelvilleQueue::empty();
+            let mut expected = 0;
+            for i in 0..1000 {
+                q = q.enq(i);
+                if i % period == 0 {
+                    assert_eq!(q.top(), &expected);
+                    q = q.deq();
+                    expected += 1;
+                }
+            }
+            assert_eq!(q.len(), 1000 - expected as usize);
+            while !q.is_empty() {
+                assert_eq!(q.top(), &expected);
+                q = q.deq();
+                expected += 1;
+            }
+            assert_eq!(expected, 1000);
+        }
+    }
+
+    #[test]
+    fn test_worst_case() {
+        // Each operation executes two steps of rotation and each step copies at most two elements
+        let mut versions = vec![HoodMelvilleQueue::empty()];
+        for i in 0..1000 {
+            let q = versions.last().unwrap().clone();
+            let before = clones();
+            let q = if i % 4 == 3 { q.deq() } else { q.enq(Counted(i)) };
+            assert!(clones() - before <= 4);
+            versions.push(q);
+        }
+        for q in &versions {
+            let before = clones();
+            let _ = q.enq(Counted(-1));
+            let _ = q.try_deq();
+            assert!(clones() - before <= 8);
+        }
+    }
+}
@@ -0,0 +1,278 @@
+// Indexed priority queue.
+//
+// Heap of key/priority pairs which can decrease or remove the priority of a key. Built on RBMap and
+// any heap implementing `HeapFamily`, using lazy deletion.
+// Amortized O(log n) insert, decrease_key and remove, O(1) find_min, and amortized delete_min in the
+// time of delete_min of the primitive heap (if old versions are not reused)
+//
+// Updates are amortized since each of them cleans up deleted entries at the top of the primitive
+// heap, which can take many deletions at once. The cleanup runs on updates rather than on find_min
+// because find_min takes `&self` and returns references into the heap, so it cannot replace the heap
+// with the cleaned one.
+
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+use leftheap::LeftHeap;
+use rbtree::RBMap;
+use traits::{Heap, HeapFamily};
+
+// Note:
+// A heap cannot find an entry by its key. So instead of removing an entry from the primitive heap,
+// the entry is inserted into the second heap of deleted entries. An entry is actually removed from
+// both heaps when it becomes the minimum of both. Since deleted entries are always in the primitive
+// heap, the minimum of the primitive heap is alive unless it is the minimum of the deleted heap.
+// Each entry is inserted and deleted at most once, so the cost of cleanup is amortized over updates.
+// The map holds the current priority of each alive key.
+pub struct IndexedHeap<K: Clone + Ord + Debug, P: Clone + Ord + Debug, H: HeapFamily = LeftHeap<P>> {
+    prios: RBMap<K, P>,
+    entries: Entries<K, P, H>,
+    deleted: Entries<K, P, H>,
+    family: PhantomData<H>,
+}
+
+type Entries<K, P, H> = <H as HeapFamily>::Heap<(P, K)>;
+
+// Implemented manually for the same reason as `BootstrappedHeap`.
+impl<K, P, H> Clone for IndexedHeap<K, P, H>
+where K: Clone + Ord + Debug, P: Clone + Ord + Debug, H: HeapFamily {
+    fn clone(&self) -> Self {
+        IndexedHeap{
+            prios: self.prios.clone(),
+            entries: self.entries.clone(),
+            deleted: self.deleted.clone(),
+            family: PhantomData,
+        }
+    }
+}
+
+impl<K, P, H> Debug for IndexedHeap<K, P, H>
+where K: Clone + Ord + Debug, P: Clone + Ord + Debug, H: HeapFamily {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IndexedHeap")
+            .field("prios", &self.prios)
+            .field("entries", &self.entries)
+            .field("deleted", &self.deleted)
+            .finish()
+    }
+}
+
+impl<K, P, H> IndexedHeap<K, P, H>
+where K: Clone + Ord + Debug, P: Clone + Ord + Debug, H: HeapFamily {
+    pub fn empty() -> Self {
+        IndexedHeap{prios: RBMap::empty(), entries: Heap::empty(), deleted: Heap::empty(), family: PhantomData}
+    }
+
+    // Removes deleted entries at the top of the primitive heap
+    fn cleanup(prios: RBMap<K, P>, mut entries: Entries<K, P, H>, mut deleted: Entries<K, P, H>) -> Self {
+        loop {
+            let stale = match (entries.try_find_min(), deleted.try_find_min()) {
+                (Some(e), Some(d)) => e == d,
+                _ => false,
+            };
+            if !stale {
+                return IndexedHeap{prios, entries, deleted, family: PhantomData};
+            }
+            entries = entries.delete_min();
+            deleted = deleted.delete_min();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prios.is_empty()
+    }
+
+    // The number of alive keys. Deleted entries which are not cleaned up yet are not counted.
+    pub fn len(&self) -> usize {
+        self.prios.len()
+    }
+
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.prios.contains_key(k)
+    }
+
+    // Current priority of the key
+    pub fn get(&self, k: &K) -> Option<&P> {
+        self.prios.get(k)
+    }
+
+    // Priority of existing key is replaced.
+    pub fn insert(&self, k: K, p: P) -> Self {
+        let deleted = match self.prios.get(&k) {
+            Some(old) => self.deleted.insert((old.clone(), k.clone())),
+            None => self.deleted.clone(),
+        };
+        let entries = self.entries.insert((p.clone(), k.clone()));
+        IndexedHeap::cleanup(self.prios.insert(k, p), entries, deleted)
+    }
+
+    // Lowers the priority of the key. Nothing is changed when the priority is not smaller than the
+    // current one. Returns None when the key is not in the heap.
+    pub fn try_decrease_key(&self, k: K, p: P) -> Option<Self> {
+        match self.prios.get(&k) {
+            None => None,
+            Some(old) if *old <= p => Some(self.clone()),
+            Some(_) => Some(self.insert(k, p)),
+        }
+    }
+
+    pub fn decrease_key(&self, k: K, p: P) -> Self {
+        self.try_decrease_key(k, p).expect("key not found!")
+    }
+
+    // Nothing is changed when the key is not in the heap.
+    pub fn remove(&self, k: &K) -> Self {
+        match self.prios.get(k) {
+            None => self.clone(),
+            Some(p) => {
+                let deleted = self.deleted.insert((p.clone(), k.clone()));
+                IndexedHeap::cleanup(self.prios.remove(k), self.entries.clone(), deleted)
+            },
+        }
+    }
+
+    // Returns the key with the minimum priority and its priority. When several keys have the same
+    // priority, the smallest key is returned.
+    pub fn try_find_min(&self) -> Option<(&K, &P)> {
+        self.entries.try_find_min().map(|(p, k)| (k, p))
+    }
+
+    pub fn find_min(&self) -> (&K, &P) {
+        self.try_find_min().expect("heap is empty!")
+    }
+
+    pub fn pop_min(&self) -> Option<((&K, &P), Self)> {
+        self.entries.pop_min().map(|((p, k), entries)| {
+            ((k, p), IndexedHeap::cleanup(self.prios.remove(k), entries, self.deleted.clone()))
+        })
+    }
+
+    pub fn try_delete_min(&self) -> Option<Self> {
+        self.pop_min().map(|(_, h)| h)
+    }
+
+    pub fn delete_min(&self) -> Self {
+        self.try_delete_min().expect("heap is empty!")
+    }
+
+    // Iterates pairs of key and priority in ascending order of priorities
+    pub fn iter(&self) -> Iter<K, P, H> {
+        Iter{heap: self.clone()}
+    }
+}
+
+// Same as `traits::HeapIter` but yields pairs of key and priority
+pub struct Iter<K: Clone + Ord + Debug, P: Clone + Ord + Debug, H: HeapFamily> {
+    heap: IndexedHeap<K, P, H>,
+}
+
+impl<K, P, H> Iterator for Iter<K, P, H>
+where K: Clone + Ord + Debug, P: Clone + Ord + Debug, H: HeapFamily {
+    type Item = (K, P);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (e, h) = match self.heap.pop_min() {
+            Some(((k, p), h)) => ((k.clone(), p.clone()), h),
+            None => return None,
+        };
+        self.heap = h;
+        Some(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use binheap::BinHeap;
+    use skewbinheap::SkewBinHeap;
+
+    #[test]
+    fn test_pop_min() {
+        let h = IndexedHeap::<&str, i32>::empty();
+        assert!(h.is_empty());
+        assert!(h.try_find_min().is_none());
+        assert!(h.try_delete_min().is_none());
+        let h = h.insert("a", 3).insert("b", 1).insert("c", 2);
+        assert_eq!(h.len(), 3);
+        assert_eq!(h.get(&"a"), Some(&3));
+        let ((k, p), h) = h.pop_min().unwrap();
+        assert_eq!((k, p), (&"b", &1));
+        assert!(!h.contains_key(&"b"));
+        assert_eq!(h.iter().collect::<Vec<_>>(), vec![("c", 2), ("a", 3)]);
+    }
+
+    #[test]
+    fn test_decrease_key_and_remove() {
+        let h = IndexedHeap::<i32, i32>::empty().insert(1, 10).insert(2, 20).insert(3, 30);
+        let h2 = h.decrease_key(3, 5);
+        assert_eq!(h2.find_min(), (&3, &5));
+        assert_eq!(h2.len(), 3);
+        // Larger priority doesn't change the heap
+        assert_eq!(h2.decrease_key(1, 15).get(&1), Some(&10));
+        assert!(h2.try_decrease_key(4, 1).is_none());
+        let h3 = h2.remove(&3).remove(&4);
+        assert_eq!(h3.find_min(), (&1, &10));
+        assert_eq!(h3.iter().collect::<Vec<_>>(), vec![(1, 10), (2, 20)]);
+        // Old versions are not changed
+        assert_eq!(h.iter().collect::<Vec<_>>(), vec![(1, 10), (2, 20), (3, 30)]);
+        assert_eq!(h2.iter().collect::<Vec<_>>(), vec![(3, 5), (1, 10), (2, 20)]);
+    }
+
+    #[test]
+    fn test_reinsert_same_priority() {
+        // The same entry can be in both the primitive heap and the deleted heap
+        let h = IndexedHeap::<i32, i32>::empty().insert(1, 1).insert(2, 2);
+        let h = h.remove(&1).insert(1, 1);
+        assert_eq!(h.find_min(), (&1, &1));
+        let h = h.insert(1, 3).remove(&2).insert(2, 2);
+        assert_eq!(h.iter().collect::<Vec<_>>(), vec![(2, 2), (1, 3)]);
+    }
+
+    fn check_same_as_vec<H: HeapFamily>() {
+        // Model of the heap: priorities indexed by keys
+        let mut v: Vec<Option<i32>> = vec![None; 50];
+        let mut h = IndexedHeap::<usize, i32, H>::empty();
+        for i in 0..2000 {
+            let k = i * 7 % 50;
+            let p = (i * 37 % 101) as i32;
+            match i % 5 {
+                0 | 1 => {
+                    h = h.insert(k, p);
+                    v[k] = Some(p);
+                },
+                2 => {
+                    h = h.remove(&k);
+                    v[k] = None;
+                },
+                3 => match v[k] {
+                    Some(old) => {
+                        h = h.decrease_key(k, p);
+                        v[k] = Some(old.min(p));
+                    },
+                    None => assert!(h.try_decrease_key(k, p).is_none()),
+                },
+                _ => {
+                    let min = v.iter().enumerate().filter_map(|(k, p)| p.map(|p| (p, k))).min();
+                    match min {
+                        Some((p, k)) => {
+                            assert_eq!(h.find_min(), (&k, &p));
+                            h = h.delete_min();
+                            v[k] = None;
+                        },
+                        None => assert!(h.is_empty()),
+                    }
+                },
+            }
+            assert_eq!(h.len(), v.iter().filter(|p| p.is_some()).count());
+        }
+        let mut expected = v.iter().enumerate().filter_map(|(k, p)| p.map(|p| (p, k))).collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(h.iter().map(|(k, p)| (p, k)).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_primitive_heaps() {
+        check_same_as_vec::<LeftHeap<i32>>();
+        check_same_as_vec::<BinHeap<i32>>();
+        check_same_as_vec::<SkewBinHeap<i32>>();
+    }
+}
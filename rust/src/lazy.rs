@@ -5,53 +5,120 @@
 use std::fmt;
 use std::fmt::Debug;
 use std::boxed::Box;
-use std::cell::RefCell;
-use std::rc::Rc;
+use ptr::Ptr;
 
-enum Thunk<'a, T: 'a + Debug> {
-    NotYet(Box<Fn() -> T + 'a>),
-    Memo(T),
+#[cfg(not(feature = "sync"))]
+use std::cell::{Cell, OnceCell};
+#[cfg(feature = "sync")]
+use std::sync::{Mutex, OnceLock};
+
+#[cfg(not(feature = "sync"))]
+type Thunk<'a, T> = Box<dyn FnOnce() -> T + 'a>;
+#[cfg(feature = "sync")]
+type Thunk<'a, T> = Box<dyn FnOnce() -> T + Send + 'a>;
+
+// Note:
+// A suspension is shared by all clones of Delayed. So once one of them is forced, the others can
+// reuse its memo without evaluating the expression again. This sharing is what the amortized bounds
+// of lazy data structures in the book rely on.
+//
+// Memo is written at most once, so it can be borrowed from `eval()` without RefCell nor unsafe.
+// With `sync` feature, OnceLock ensures that the expression is evaluated only once even if multiple
+// threads force the same suspension at the same time.
+#[cfg(not(feature = "sync"))]
+struct Suspension<'a, T: 'a> {
+    memo: OnceCell<T>,
+    thunk: Cell<Option<Thunk<'a, T>>>,
 }
 
-// Note: Cannot derive std::format::Debug because of Fn.
-impl<'a, T> fmt::Debug for Thunk<'a, T>
-where T: 'a + Debug {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &NotYet(_) => write!(f, "Thunk {{ (not yet...) }}"),
-            &Memo(ref v) => write!(f, "Thunk {{ {:?} }}", v),
-        }
-    }
+#[cfg(feature = "sync")]
+struct Suspension<'a, T: 'a> {
+    memo: OnceLock<T>,
+    thunk: Mutex<Option<Thunk<'a, T>>>,
 }
 
-use self::Thunk::*;
+impl<'a, T: 'a> Suspension<'a, T> {
+    #[cfg(not(feature = "sync"))]
+    fn new(thunk: Thunk<'a, T>) -> Self {
+        Suspension{memo: OnceCell::new(), thunk: Cell::new(Some(thunk))}
+    }
+
+    #[cfg(feature = "sync")]
+    fn new(thunk: Thunk<'a, T>) -> Self {
+        Suspension{memo: OnceLock::new(), thunk: Mutex::new(Some(thunk))}
+    }
+
+    #[cfg(not(feature = "sync"))]
+    fn constant(v: T) -> Self {
+        Suspension{memo: OnceCell::from(v), thunk: Cell::new(None)}
+    }
+
+    #[cfg(feature = "sync")]
+    fn constant(v: T) -> Self {
+        Suspension{memo: OnceLock::from(v), thunk: Mutex::new(None)}
+    }
+
+    #[cfg(not(feature = "sync"))]
+    fn take_thunk(&self) -> Option<Thunk<'a, T>> {
+        self.thunk.take()
+    }
+
+    #[cfg(feature = "sync")]
+    fn take_thunk(&self) -> Option<Thunk<'a, T>> {
+        self.thunk.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    fn eval(&self) -> &T {
+        self.memo.get_or_init(|| {
+            let invoke = self.take_thunk().expect("Delayed expression was already forced and panicked");
+            invoke()
+        })
+    }
+}
 
-// Note:
-// Need to use Box<T> instead of Rc<T> here for implementing Clone trait. Clone is needed for copying
-// elements of immutable data structures.
-#[derive(Clone, Debug)]
 pub struct Delayed<'a, T: 'a + Debug> {
-    thunk: RefCell<Rc<Thunk<'a, T>>>
+    susp: Ptr<Suspension<'a, T>>,
+}
+
+// Note: Cannot derive Clone because it requires T: Clone though only the pointer is cloned.
+impl<'a, T: 'a + Debug> Clone for Delayed<'a, T> {
+    fn clone(&self) -> Self {
+        Delayed { susp: self.susp.clone() }
+    }
+}
+
+// Note: Cannot derive std::format::Debug because of FnOnce.
+impl<'a, T> fmt::Debug for Delayed<'a, T>
+where T: 'a + Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.susp.memo.get() {
+            None => write!(f, "Delayed {{ (not yet...) }}"),
+            Some(v) => write!(f, "Delayed {{ {:?} }}", v),
+        }
+    }
 }
 
 impl<'a, T: 'a + Debug> Delayed<'a, T> {
-    pub fn new<F>(f: F) -> Self where F: Fn() -> T + 'a {
-        Delayed { thunk: RefCell::new(Rc::new(NotYet(Box::new(f)))) }
+    #[cfg(not(feature = "sync"))]
+    pub fn new<F>(f: F) -> Self where F: FnOnce() -> T + 'a {
+        Delayed { susp: Ptr::new(Suspension::new(Box::new(f))) }
+    }
+
+    #[cfg(feature = "sync")]
+    pub fn new<F>(f: F) -> Self where F: FnOnce() -> T + Send + 'a {
+        Delayed { susp: Ptr::new(Suspension::new(Box::new(f))) }
     }
 
     pub fn constant(v: T) -> Self {
-        Delayed { thunk: RefCell::new(Rc::new(Memo(v))) }
+        Delayed { susp: Ptr::new(Suspension::constant(v)) }
     }
 
     pub fn force(&self) {
-        let thunk = &mut *self.thunk.borrow_mut();
-        let val = match **thunk {
-            NotYet(ref invoke) => {
-                Rc::new(Memo(invoke()))
-            },
-            Memo(_) => return,
-        };
-        *thunk = val;
+        self.eval();
+    }
+
+    pub fn is_forced(&self) -> bool {
+        self.susp.memo.get().is_some()
     }
 
     // Note:
@@ -60,30 +127,32 @@ impl<'a, T: 'a + Debug> Delayed<'a, T> {
     // implements Deref cannot be obtained because getting reference with `&` coerces into the value
     // into target type.
     pub fn eval(&self) -> &T {
-        self.force();
-        let thunk = unsafe {
-            self.thunk.as_ptr().as_ref().unwrap()
-        };
-        match **thunk {
-            Memo(ref v) => v,
-            _ => unreachable!(),
-        }
+        self.susp.eval()
+    }
+
+    // Takes the memo out when no other clone shares the suspension. It is used for dropping a long
+    // chain of suspensions in a loop instead of recursion.
+    pub fn take_unique(&mut self) -> Option<T> {
+        Ptr::get_mut(&mut self.susp).and_then(|s| s.memo.take())
+    }
+
+    // Same as `take_unique()` but forces the suspension first when it is not forced yet.
+    pub fn force_unique(&mut self) -> Option<T> {
+        Ptr::get_mut(&mut self.susp).and_then(|s| {
+            s.eval();
+            s.memo.take()
+        })
     }
 }
 
 // Note:
-// I needed Rc<RefCell<T>> for implementing eval_mut().
-// We could have implemented eval_mut() when using Box instead of Rc previously. This had been because
-// Box propagates mutability of content (`mut Box<T>` means `mut Box<mut T>`) but Rc does not. In other
-// words, Rc does not implement eval_mut().
-//
 // Since I don't use Delayed with mutable expression because we implement immutabile data structures,
-// I skipped to implement DrefMut simply.
+// I skipped to implement eval_mut() and DerefMut simply.
 
 #[macro_export]
 macro_rules! lazily {
     ($($b:tt)+) => {
-        self::Delayed::new(move || { $($b)+ })
+        $crate::lazy::Delayed::new(move || { $($b)+ })
     }
 }
 
@@ -118,9 +187,52 @@ mod tests {
         assert_eq!(*prime1000.eval(), 7927);
         assert_eq!(*prime1000.eval(), 7927);
 
-        // Inner infinite loop will never be evaluated until the delayed computation is invoked.
+        // Inner panic will never be evaluated until the delayed computation is invoked.
         let _ = lazily! {
-            loop {}
+            panic!("never evaluated")
         };
     }
+
+    #[cfg(not(feature = "sync"))]
+    #[test]
+    fn test_shared_memo() {
+        use std::cell::Cell;
+
+        let count = Cell::new(0);
+        let d = Delayed::new(|| {
+            count.set(count.get() + 1);
+            42
+        });
+        let d2 = d.clone();
+        assert!(!d2.is_forced());
+        assert_eq!(*d.eval(), 42);
+        // Clones share the memo
+        assert!(d2.is_forced());
+        assert_eq!(*d2.eval(), 42);
+        assert_eq!(count.get(), 1);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_shared_memo() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::thread;
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let c = count.clone();
+        let d: Delayed<'static, i32> = Delayed::new(move || {
+            c.fetch_add(1, Ordering::SeqCst);
+            42
+        });
+        let handles = (0..4).map(|_| {
+            let d = d.clone();
+            thread::spawn(move || assert_eq!(*d.eval(), 42))
+        }).collect::<Vec<_>>();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert!(d.is_forced());
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
 }
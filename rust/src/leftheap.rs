@@ -3,92 +3,134 @@
 // Leftist heap
 
 use std::fmt::Debug;
-use std::rc::Rc;
 use std::cmp::Ord;
+use ptr::Ptr;
+use traits;
 
 // Right spine is a rank of heap
 
 #[derive(Debug, Clone)]
-enum LeftHeap<T: Ord + Clone + Debug> {
+enum Node<T: Ord + Clone + Debug> {
     Leaf,
-    Knot(i32, T, Rc<LeftHeap<T>>, Rc<LeftHeap<T>>),
+    Knot(i32, T, Link<T>, Link<T>),
 }
 
-impl<T> LeftHeap<T>
-where T: Ord + Clone + Debug {
-    pub fn empty() -> Self {
-        LeftHeap::Leaf
-    }
+type Link<T> = Ptr<Node<T>>;
 
-    pub fn rank(&self) -> i32 {
-        match *self {
-            LeftHeap::Leaf => 0,
-            LeftHeap::Knot(r, _, _, _) => r,
-        }
+impl<T> Node<T>
+where T: Ord + Clone + Debug {
+    fn singleton(x: T) -> Self {
+        Node::Knot(1, x, Ptr::new(Node::Leaf), Ptr::new(Node::Leaf))
     }
 
-    pub fn is_empty(&self) -> bool {
-        // T does not derive PartialEq so == is not available
+    fn rank(&self) -> i32 {
         match *self {
-            LeftHeap::Leaf => true,
-            _ => false,
+            Node::Leaf => 0,
+            Node::Knot(r, _, _, _) => r,
         }
     }
 
-    fn make_knot(x: T, a: Rc<LeftHeap<T>>, b: Rc<LeftHeap<T>>) -> Self {
+    fn make_knot(x: T, a: Link<T>, b: Link<T>) -> Self {
         if a.rank() >= b.rank() {
-            LeftHeap::Knot(b.rank() + 1, x, a, b)
+            Node::Knot(b.rank() + 1, x, a, b)
         } else {
-            LeftHeap::Knot(a.rank() + 1, x, b, a)
+            Node::Knot(a.rank() + 1, x, b, a)
         }
     }
 
-    pub fn merge(&self, rhs: &Self) -> Self {
+    fn merge(&self, rhs: &Self) -> Self {
         match (self, rhs) {
-            (&LeftHeap::Leaf, _) => rhs.clone(),
-            (_, &LeftHeap::Leaf) => self.clone(),
-            (&LeftHeap::Knot(_, ref x, ref a1, ref b1), &LeftHeap::Knot(_, ref y, ref a2, ref b2)) => {
+            (&Node::Leaf, _) => rhs.clone(),
+            (_, &Node::Leaf) => self.clone(),
+            (Node::Knot(_, x, a1, b1), Node::Knot(_, y, a2, b2)) => {
                 if x <= y {
-                    LeftHeap::make_knot(x.clone(), a1.clone(), Rc::new(b1.merge(rhs)))
+                    Node::make_knot(x.clone(), a1.clone(), Ptr::new(b1.merge(rhs)))
                 } else {
-                    LeftHeap::make_knot(y.clone(), a2.clone(), Rc::new(self.merge(b2)))
+                    Node::make_knot(y.clone(), a2.clone(), Ptr::new(self.merge(b2)))
                 }
             }
         }
     }
 
     // exersize 3.2: implement insert() without merge()
-    pub fn insert(&self, v: T) -> Self {
+    fn insert(&self, v: T) -> Self {
         match *self {
-            LeftHeap::Leaf => LeftHeap::Knot(1, v, Rc::new(LeftHeap::Leaf), Rc::new(LeftHeap::Leaf)),
-            LeftHeap::Knot(_, ref x, ref a, ref b) => {
+            Node::Leaf => Node::singleton(v),
+            Node::Knot(_, ref x, ref a, ref b) => {
                 if *x <= v {
-                    LeftHeap::make_knot(x.clone(), a.clone(), Rc::new(b.insert(v)))
+                    Node::make_knot(x.clone(), a.clone(), Ptr::new(b.insert(v)))
                 } else {
-                    LeftHeap::make_knot(v, Rc::new(LeftHeap::Leaf), Rc::new(self.clone()))
+                    Node::make_knot(v, Ptr::new(Node::Leaf), Ptr::new(self.clone()))
                 }
             },
         }
     }
+}
 
-    pub fn find_min(&self) -> &T {
-        match *self {
-            LeftHeap::Leaf => panic!("heap is empty!"),
-            LeftHeap::Knot(_, ref x, _, _) => x,
+// Nodes of the heap are hidden. The number of elements is cached to make `len` O(1).
+#[derive(Debug, Clone)]
+pub struct LeftHeap<T: Ord + Clone + Debug> {
+    root: Node<T>,
+    size: usize,
+}
+
+impl<T> LeftHeap<T>
+where T: Ord + Clone + Debug {
+    pub fn empty() -> Self {
+        LeftHeap{root: Node::Leaf, size: 0}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn merge(&self, rhs: &Self) -> Self {
+        LeftHeap{root: self.root.merge(&rhs.root), size: self.size + rhs.size}
+    }
+
+    pub fn insert(&self, v: T) -> Self {
+        LeftHeap{root: self.root.insert(v), size: self.size + 1}
+    }
+
+    pub fn pop_min(&self) -> Option<(&T, Self)> {
+        match self.root {
+            Node::Leaf => None,
+            Node::Knot(_, ref x, ref a, ref b) => Some((x, LeftHeap{root: a.merge(b), size: self.size - 1})),
         }
     }
 
-    pub fn delete_min(&self) -> Self {
-        match *self {
-            LeftHeap::Leaf => panic!("heap is empty!"),
-            LeftHeap::Knot(_, _, ref a, ref b) => a.merge(b)
+    pub fn try_find_min(&self) -> Option<&T> {
+        match self.root {
+            Node::Leaf => None,
+            Node::Knot(_, ref x, _, _) => Some(x),
         }
     }
 
+    pub fn try_delete_min(&self) -> Option<Self> {
+        self.pop_min().map(|(_, h)| h)
+    }
+
+    pub fn find_min(&self) -> &T {
+        self.try_find_min().expect("heap is empty!")
+    }
+
+    pub fn delete_min(&self) -> Self {
+        self.try_delete_min().expect("heap is empty!")
+    }
+
+    // Iterates elements in ascending order
+    pub fn iter(&self) -> traits::HeapIter<T, Self> {
+        traits::HeapIter::new(self.clone())
+    }
+
     // exersize 3.3
     pub fn from_slice(a: &[T]) -> Self {
         let mut heaps = a.iter()
-            .map(|e| LeftHeap::Knot(1, e.clone(), Rc::new(LeftHeap::Leaf), Rc::new(LeftHeap::Leaf)))
+            .map(|e| Node::singleton(e.clone()))
             .collect::<Vec<_>>();
 
         while heaps.len() > 1 {
@@ -101,8 +143,44 @@ where T: Ord + Clone + Debug {
             }).collect::<Vec<_>>();
         }
 
-        heaps.pop().expect("heap is empty")
+        LeftHeap{root: heaps.pop().unwrap_or(Node::Leaf), size: a.len()}
+    }
+}
+
+impl<T> traits::Heap<T> for LeftHeap<T>
+where T: Ord + Clone + Debug {
+    fn empty() -> Self {
+        LeftHeap::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn insert(&self, x: T) -> Self {
+        self.insert(x)
+    }
+
+    fn merge(&self, other: &Self) -> Self {
+        self.merge(other)
     }
+
+    fn try_find_min(&self) -> Option<&T> {
+        self.try_find_min()
+    }
+
+    fn try_delete_min(&self) -> Option<Self> {
+        self.try_delete_min()
+    }
+
+    fn pop_min(&self) -> Option<(&T, Self)> {
+        self.pop_min()
+    }
+}
+
+impl<T> traits::HeapFamily for LeftHeap<T>
+where T: Ord + Clone + Debug {
+    type Heap<E: Clone + Ord + Debug> = LeftHeap<E>;
 }
 
 #[cfg(test)]
@@ -117,6 +195,18 @@ mod tests {
         assert!(e.is_empty());
     }
 
+    #[test]
+    fn test_pop_min() {
+        let h = LeftHeap::<i32>::empty();
+        assert!(h.try_find_min().is_none());
+        assert!(h.try_delete_min().is_none());
+        assert!(h.pop_min().is_none());
+        let h = h.insert(3).insert(1).insert(2);
+        let (x, h) = h.pop_min().unwrap();
+        assert_eq!(x, &1);
+        assert_eq!(h.try_find_min(), Some(&2));
+    }
+
     #[test]
     fn test_merge() {
         let h1 = LeftHeap::empty().insert(4).insert(6).insert(10);
@@ -156,5 +246,24 @@ mod tests {
             h = h.delete_min();
         }
         assert!(h.is_empty());
+        assert!(LeftHeap::<i32>::from_slice(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_len() {
+        let h = LeftHeap::from_slice(&[4, 10, 6, 1, 9]);
+        assert_eq!(h.len(), 5);
+        let h = h.merge(&LeftHeap::empty().insert(3).insert(3));
+        assert_eq!(h.len(), 7);
+        assert_eq!(h.delete_min().len(), 6);
+    }
+
+    #[test]
+    fn test_iter() {
+        let h = LeftHeap::from_slice(&[4, 10, 6, 1, 9]);
+        assert_eq!(h.iter().collect::<Vec<_>>(), vec![1, 4, 6, 9, 10]);
+        // The heap itself is not changed
+        assert_eq!(h.find_min(), &1);
+        assert_eq!(h.len(), 5);
     }
 }
@@ -1,11 +1,31 @@
+pub mod ptr;
 #[macro_use]
-mod lazy;
+pub mod lazy;
 
+pub mod traits;
+#[cfg(test)]
+mod counted;
 pub mod list;
+pub mod binrandomaccesslist;
+pub mod skewbinrandomaccesslist;
 pub mod bintree;
 pub mod leftheap;
+pub mod weightleftheap;
 pub mod binheap;
+pub mod skewbinheap;
+pub mod bootstrappedheap;
+pub mod splayheap;
+pub mod pairingheap;
 pub mod rbtree;
+pub mod indexedheap;
 pub mod stream;
 pub mod queue;
+pub mod bankersqueue;
+pub mod physicistsqueue;
+pub mod realtimequeue;
+pub mod hoodmelvillequeue;
 pub mod deque;
+pub mod bankersdeque;
+pub mod realtimedeque;
+pub mod catlist;
+pub mod catdeque;
@@ -1,7 +1,10 @@
 // p.17 - p.21
 
 use std::fmt::Debug;
-use std::rc::Rc;
+use std::iter::FromIterator;
+use std::mem;
+use ptr::Ptr;
+use traits;
 
 #[derive(Clone, Debug)]
 pub enum Node<T: Clone + Debug> {
@@ -11,12 +14,12 @@ pub enum Node<T: Clone + Debug> {
 
 use self::Node::*;
 
-// Just a wrapper of Rc<Node<T>> because `impl` cannot be used for external type `Rc`.
+// Just a wrapper of Ptr<Node<T>> because `impl` cannot be used for external types `Rc` and `Arc`.
 #[derive(Clone, Debug)]
-pub struct List<T: Clone + Debug>(pub Rc<Node<T>>);
+pub struct List<T: Clone + Debug>(pub Ptr<Node<T>>);
 
 fn list<T: Clone + Debug>(n: Node<T>) -> List<T> {
-    List(Rc::new(n))
+    List(Ptr::new(n))
 }
 
 impl<T> List<T>
@@ -31,72 +34,194 @@ where T: Clone + Debug {
     }
 
     pub fn is_empty(&self) -> bool {
-        match *self.0 {
-            Nil => true,
-            _ => false,
-        }
+        matches!(*self.0, Nil)
     }
 
     pub fn cons(&self, v: T) -> Self {
         list(Cons(v, self.clone()))
     }
 
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter{cur: self}
+    }
+
+    // O(n) since the length is not cached in cells.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
     pub fn root(&self) -> &Node<T> {
-        &*self.0
+        &self.0
     }
 
-    pub fn head(&self) -> &T {
+    pub fn uncons(&self) -> Option<(&T, &Self)> {
         match *self.0 {
-            Nil => panic!("Node is empty!"),
-            Cons(ref x, _) => x,
+            Nil => None,
+            Cons(ref x, ref xs) => Some((x, xs)),
         }
     }
 
+    pub fn try_head(&self) -> Option<&T> {
+        self.uncons().map(|(x, _)| x)
+    }
+
+    pub fn try_tail(&self) -> Option<&Self> {
+        self.uncons().map(|(_, xs)| xs)
+    }
+
+    pub fn head(&self) -> &T {
+        self.try_head().expect("Node is empty!")
+    }
+
     pub fn tail(&self) -> &Self {
-        match *self.0 {
-            Nil => panic!("Node is empty!"),
-            Cons(_, ref xs) => xs,
-        }
+        self.try_tail().expect("Node is empty!")
     }
 
     // Preceding list can be shared and don't need to be cloned.
+    // Note: All operations below walk the list with loops instead of recursion so that they don't
+    // overflow the stack on long lists.
     pub fn concat(&self, other: &List<T>) -> Self {
-        match *self.0 {
-            Nil => other.clone(),
-            Cons(ref x, ref xs) => xs.concat(other).cons(x.clone()),
-        }
+        let xs = self.iter().collect::<Vec<_>>();
+        xs.into_iter().rev().fold(other.clone(), |l, x| l.cons(x.clone()))
     }
 
     // Cells after `idx` can be shared (don't need to copy)
     pub fn update_at(&self, idx: u32, v: T) -> Self {
-        match *self.0 {
-            Nil => panic!("Node is empty!"),
-            Cons(ref x, ref xs) =>
-                if idx == 0 {
-                    list(Cons(v, xs.clone()))
-                } else {
-                    xs.update_at(idx-1, v).cons(x.clone())
-                },
+        let mut prefix = Vec::new();
+        let mut cur = self;
+        for _ in 0..idx {
+            let (x, xs) = cur.uncons().expect("Node is empty!");
+            prefix.push(x);
+            cur = xs;
         }
+        let updated = cur.tail().cons(v);
+        prefix.into_iter().rev().fold(updated, |l, x| l.cons(x.clone()))
     }
 
     // Elements of returned list are shared.
     pub fn suffixes(&self) -> List<Self> {
-        match *self.0 {
-            Nil => List::one(List::empty()),
-            Cons(_, ref xs) => xs.suffixes().cons(self.clone()),
+        let mut suffixes = vec![];
+        let mut cur = self;
+        while let Some((_, xs)) = cur.uncons() {
+            suffixes.push(cur);
+            cur = xs;
         }
+        suffixes.into_iter().rev().fold(List::one(List::empty()), |l, xs| l.cons(xs.clone()))
     }
 
-    fn rev_impl(&self, ret: Self) -> Self {
-        match *self.0 {
-            Nil => ret,
-            Cons(ref x, ref xs) => xs.rev_impl(ret.cons(x.clone()))
+    pub fn rev(&self) -> Self {
+        self.iter().fold(List::empty(), |l, x| l.cons(x.clone()))
+    }
+}
+
+// Dropping Ptr<Node<T>> recursively drops the rest of cells and overflows the stack on a long list.
+// Here cells which are owned only by this list are unlinked one by one in a loop instead. Dropping
+// stops at the first cell shared with other lists.
+impl<T> Drop for List<T>
+where T: Clone + Debug {
+    fn drop(&mut self) {
+        let mut next = match Ptr::get_mut(&mut self.0) {
+            Some(n) => mem::replace(n, Nil),
+            None => return,
+        };
+        while let Cons(_, mut xs) = next {
+            next = match Ptr::get_mut(&mut xs.0) {
+                Some(n) => mem::replace(n, Nil),
+                None => return,
+            };
         }
     }
+}
 
-    pub fn rev(&self) -> Self {
-        self.rev_impl(List::empty())
+// Walks cells shared via Ptr without cloning elements.
+pub struct Iter<'a, T: 'a + Clone + Debug> {
+    cur: &'a List<T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T>
+where T: Clone + Debug {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.cur.uncons().map(|(x, xs)| {
+            self.cur = xs;
+            x
+        })
+    }
+}
+
+// Used by queues and deques made of a front list and a reversed back list. Elements of the front list
+// are visited in order, then elements of the back list are visited in reverse order. Since lists are
+// singly linked, the back list is collected before iteration.
+pub struct FrontBackIter<'a, T: 'a + Clone + Debug> {
+    front: Iter<'a, T>,
+    back: Vec<&'a T>,
+}
+
+impl<'a, T> FrontBackIter<'a, T>
+where T: Clone + Debug {
+    pub fn new(front: &'a List<T>, back: &'a List<T>) -> Self {
+        FrontBackIter{front: front.iter(), back: back.iter().collect()}
+    }
+}
+
+impl<'a, T> Iterator for FrontBackIter<'a, T>
+where T: Clone + Debug {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.front.next().or_else(|| self.back.pop())
+    }
+}
+
+impl<'a, T> IntoIterator for &'a List<T>
+where T: Clone + Debug {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// Order of elements is preserved: the first element of the iterator is the head of the list.
+impl<T> FromIterator<T> for List<T>
+where T: Clone + Debug {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let v = iter.into_iter().collect::<Vec<_>>();
+        v.into_iter().rev().fold(List::empty(), |l, x| l.cons(x))
+    }
+}
+
+// Appends elements to the end of the list. Like `concat`, all cells of the list are copied.
+impl<T> Extend<T> for List<T>
+where T: Clone + Debug {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let tail = iter.into_iter().collect::<List<_>>();
+        *self = self.concat(&tail);
+    }
+}
+
+impl<T> traits::Stack<T> for List<T>
+where T: Clone + Debug {
+    fn empty() -> Self {
+        List::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn cons(&self, x: T) -> Self {
+        self.cons(x)
+    }
+
+    fn try_head(&self) -> Option<&T> {
+        self.try_head()
+    }
+
+    fn try_tail(&self) -> Option<Self> {
+        self.try_tail().cloned()
     }
 }
 
@@ -123,6 +248,20 @@ mod tests {
         assert_eq!(e.tail().head(), &1);
     }
 
+    #[test]
+    fn test_uncons() {
+        let e = List::<i32>::empty();
+        assert!(e.uncons().is_none());
+        assert!(e.try_head().is_none());
+        assert!(e.try_tail().is_none());
+        // 2, 1
+        let e = e.cons(1).cons(2);
+        let (x, xs) = e.uncons().unwrap();
+        assert_eq!(x, &2);
+        assert_eq!(xs.try_head(), Some(&1));
+        assert!(xs.try_tail().unwrap().is_empty());
+    }
+
     #[test]
     fn test_concat() {
         // 2, 1
@@ -168,17 +307,75 @@ mod tests {
         assert!(List::<i32>::empty().rev().is_empty());
     }
 
+    #[test]
+    fn test_iter() {
+        // 2, 1, 4, 3
+        let e = List::one(3).cons(4).cons(1).cons(2);
+        assert_eq!(e.iter().cloned().collect::<Vec<_>>(), vec![2, 1, 4, 3]);
+        let mut v = vec![];
+        for x in &e {
+            v.push(*x);
+        }
+        assert_eq!(v, vec![2, 1, 4, 3]);
+        assert_eq!(e.len(), 4);
+        assert_eq!(List::<i32>::empty().len(), 0);
+        assert!(List::<i32>::empty().iter().next().is_none());
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let e = (1..5).collect::<List<_>>();
+        assert_eq!(e.head(), &1);
+        assert_eq!(e.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!(Vec::<i32>::new().into_iter().collect::<List<_>>().is_empty());
+
+        let mut e = e;
+        e.extend(vec![5, 6]);
+        assert_eq!(e.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_long_list() {
+        const N: i32 = 1_000_000;
+        let e = (0..N).collect::<List<_>>();
+        assert_eq!(e.len(), N as usize);
+
+        let r = e.rev();
+        assert_eq!(r.head(), &(N - 1));
+
+        let c = e.concat(&r);
+        assert_eq!(c.len(), 2 * N as usize);
+
+        let u = e.update_at(N as u32 - 1, -1);
+        assert_eq!(u.iter().last(), Some(&-1));
+        assert_eq!(e.iter().last(), Some(&(N - 1)));
+
+        let s = e.suffixes();
+        assert_eq!(s.len(), N as usize + 1);
+        assert_eq!(s.tail().head().head(), &1);
+
+        // Drop lists sharing their cells in various orders
+        drop(e);
+        drop(s);
+        drop(c);
+        drop(r);
+        drop(u);
+    }
+
+    #[test]
+    fn test_drop_long_list() {
+        let mut e = List::empty();
+        for i in 0..1_000_000 {
+            e = e.cons(i);
+        }
+        drop(e);
+    }
+
     #[test]
     fn test_root() {
         let e = List::empty();
-        match *e.root() {
-            Nil => {},
-            _ => assert!(false),
-        };
+        assert!(matches!(*e.root(), Nil));
         let e = e.cons(1);
-        match *e.root() {
-            Nil => assert!(false),
-            _ => {},
-        };
+        assert!(matches!(*e.root(), Cons(1, _)));
     }
 }
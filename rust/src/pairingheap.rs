@@ -0,0 +1,369 @@
+// Section 5.5 and Section 6.5
+//
+// Pairing heap.
+// PairingHeap: O(1) insert, merge and find_min, and amortized O(log n) delete_min (if old versions are
+// not reused)
+// LazyPairingHeap: Same bounds as PairingHeap even if old versions are used persistently
+
+use std::fmt::Debug;
+use std::cmp::Ord;
+use std::mem;
+use lazy::Delayed;
+use list::{self, List};
+use ptr::{Ptr, Shareable};
+use traits;
+
+#[derive(Debug, Clone)]
+enum Node<T: Ord + Clone + Debug> {
+    Empty,
+    Tree(T, List<Node<T>>),
+}
+
+impl<T> Node<T>
+where T: Ord + Clone + Debug {
+    fn merge(&self, rhs: &Self) -> Self {
+        match (self, rhs) {
+            (_, &Node::Empty) => self.clone(),
+            (&Node::Empty, _) => rhs.clone(),
+            (Node::Tree(x, hs1), Node::Tree(y, hs2)) => {
+                if x <= y {
+                    Node::Tree(x.clone(), hs1.cons(rhs.clone()))
+                } else {
+                    Node::Tree(y.clone(), hs2.cons(self.clone()))
+                }
+            },
+        }
+    }
+
+    // Merges children in pairs from left to right, then merges the results from right to left.
+    // Written with a loop since a root can have O(n) children (e.g. after inserting sorted elements).
+    fn merge_pairs(hs: &List<Node<T>>) -> Self {
+        let hs = hs.iter().collect::<Vec<_>>();
+        let pairs = hs.chunks(2).map(|pair| {
+            if pair.len() == 1 {
+                pair[0].clone()
+            } else {
+                pair[0].merge(pair[1])
+            }
+        }).collect::<Vec<_>>();
+        pairs.into_iter().rev().fold(Node::Empty, |acc, h| h.merge(&acc))
+    }
+}
+
+// Nodes of the heap are hidden. The number of elements is cached to make `len` O(1).
+#[derive(Debug, Clone)]
+pub struct PairingHeap<T: Ord + Clone + Debug> {
+    root: Node<T>,
+    size: usize,
+}
+
+impl<T> PairingHeap<T>
+where T: Ord + Clone + Debug {
+    pub fn empty() -> Self {
+        PairingHeap{root: Node::Empty, size: 0}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn merge(&self, rhs: &Self) -> Self {
+        PairingHeap{root: self.root.merge(&rhs.root), size: self.size + rhs.size}
+    }
+
+    pub fn insert(&self, v: T) -> Self {
+        PairingHeap{root: Node::Tree(v, List::empty()).merge(&self.root), size: self.size + 1}
+    }
+
+    pub fn pop_min(&self) -> Option<(&T, Self)> {
+        match self.root {
+            Node::Empty => None,
+            Node::Tree(ref x, ref hs) => Some((x, PairingHeap{root: Node::merge_pairs(hs), size: self.size - 1})),
+        }
+    }
+
+    pub fn try_find_min(&self) -> Option<&T> {
+        match self.root {
+            Node::Empty => None,
+            Node::Tree(ref x, _) => Some(x),
+        }
+    }
+
+    pub fn try_delete_min(&self) -> Option<Self> {
+        self.pop_min().map(|(_, h)| h)
+    }
+
+    pub fn find_min(&self) -> &T {
+        self.try_find_min().expect("heap is empty!")
+    }
+
+    pub fn delete_min(&self) -> Self {
+        self.try_delete_min().expect("heap is empty!")
+    }
+
+    // Iterates elements in ascending order
+    pub fn iter(&self) -> traits::HeapIter<T, Self> {
+        traits::HeapIter::new(self.clone())
+    }
+
+    pub fn from_slice(a: &[T]) -> Self {
+        a.iter().fold(PairingHeap::empty(), |h, x| h.insert(x.clone()))
+    }
+}
+
+// A tree can be deep (e.g. after inserting elements in descending order) and dropping it recursively
+// overflows the stack. Lists of children owned only by this heap are unlinked in a loop.
+impl<T> Drop for PairingHeap<T>
+where T: Ord + Clone + Debug {
+    fn drop(&mut self) {
+        let mut lists = match mem::replace(&mut self.root, Node::Empty) {
+            Node::Tree(_, hs) => vec![hs],
+            Node::Empty => return,
+        };
+        while let Some(mut hs) = lists.pop() {
+            if let Some(n) = Ptr::get_mut(&mut hs.0) {
+                if let list::Node::Cons(h, rest) = mem::replace(n, list::Node::Nil) {
+                    lists.push(rest);
+                    if let Node::Tree(_, children) = h {
+                        lists.push(children);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T> traits::Heap<T> for PairingHeap<T>
+where T: Ord + Clone + Debug {
+    fn empty() -> Self {
+        PairingHeap::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn insert(&self, x: T) -> Self {
+        self.insert(x)
+    }
+
+    fn merge(&self, other: &Self) -> Self {
+        self.merge(other)
+    }
+
+    fn try_find_min(&self) -> Option<&T> {
+        self.try_find_min()
+    }
+
+    fn try_delete_min(&self) -> Option<Self> {
+        self.try_delete_min()
+    }
+
+    fn pop_min(&self) -> Option<(&T, Self)> {
+        self.pop_min()
+    }
+}
+
+impl<T> traits::HeapFamily for PairingHeap<T>
+where T: Ord + Clone + Debug {
+    type Heap<E: Clone + Ord + Debug> = PairingHeap<E>;
+}
+
+// Note:
+// Each node has at most one child heap which is not suspended. When linking a heap to a node which
+// already has the child, both are merged with the suspended heap lazily. Merging pairs is done by
+// these suspensions instead of `merge_pairs` on `delete_min`, so the cost is shared by all versions.
+#[derive(Debug, Clone)]
+enum LazyNode<'a, T: 'a + Ord + Clone + Debug + Shareable> {
+    Empty,
+    Tree(T, Ptr<LazyNode<'a, T>>, Delayed<'a, LazyNode<'a, T>>),
+}
+
+impl<'a, T> LazyNode<'a, T>
+where T: 'a + Ord + Clone + Debug + Shareable {
+    fn link(&self, a: &Self) -> Self {
+        match *self {
+            LazyNode::Tree(ref x, ref b, ref m) => match **b {
+                LazyNode::Empty => LazyNode::Tree(x.clone(), Ptr::new(a.clone()), m.clone()),
+                LazyNode::Tree(..) => {
+                    // The suspension in the node is forced before the new one captures it. Otherwise
+                    // suspensions would nest as deep as the number of links (e.g. after inserting
+                    // elements in ascending order) and forcing the outermost one would recurse into
+                    // all of them.
+                    m.force();
+                    let (a, b, m) = (a.clone(), b.clone(), m.clone());
+                    LazyNode::Tree(x.clone(), Ptr::new(LazyNode::Empty), lazily!{
+                        a.merge(&b).merge(m.eval())
+                    })
+                },
+            },
+            LazyNode::Empty => unreachable!("cannot link to empty heap"),
+        }
+    }
+
+    fn merge(&self, rhs: &Self) -> Self {
+        match (self, rhs) {
+            (_, &LazyNode::Empty) => self.clone(),
+            (&LazyNode::Empty, _) => rhs.clone(),
+            (LazyNode::Tree(x, _, _), LazyNode::Tree(y, _, _)) => {
+                if x <= y {
+                    self.link(rhs)
+                } else {
+                    rhs.link(self)
+                }
+            },
+        }
+    }
+}
+
+// A tree can be deep as well as `PairingHeap`. Children and forced suspensions owned only by the
+// dropped node are moved out and unlinked in a loop.
+impl<'a, T> Drop for LazyNode<'a, T>
+where T: 'a + Ord + Clone + Debug + Shareable {
+    fn drop(&mut self) {
+        fn take_children<'a, T>(n: &mut LazyNode<'a, T>, nodes: &mut Vec<LazyNode<'a, T>>)
+        where T: 'a + Ord + Clone + Debug + Shareable {
+            if let LazyNode::Tree(_, ref mut b, ref mut m) = *n {
+                if let Some(b) = Ptr::get_mut(b) {
+                    nodes.push(mem::replace(b, LazyNode::Empty));
+                }
+                nodes.extend(m.take_unique());
+            }
+        }
+
+        let mut nodes = vec![];
+        take_children(self, &mut nodes);
+        while let Some(mut n) = nodes.pop() {
+            take_children(&mut n, &mut nodes);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LazyPairingHeap<'a, T: 'a + Ord + Clone + Debug + Shareable> {
+    root: LazyNode<'a, T>,
+    size: usize,
+}
+
+impl<'a, T> LazyPairingHeap<'a, T>
+where T: 'a + Ord + Clone + Debug + Shareable {
+    pub fn empty() -> Self {
+        LazyPairingHeap{root: LazyNode::Empty, size: 0}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn merge(&self, rhs: &Self) -> Self {
+        LazyPairingHeap{root: self.root.merge(&rhs.root), size: self.size + rhs.size}
+    }
+
+    pub fn insert(&self, v: T) -> Self {
+        let t = LazyNode::Tree(v, Ptr::new(LazyNode::Empty), Delayed::constant(LazyNode::Empty));
+        LazyPairingHeap{root: t.merge(&self.root), size: self.size + 1}
+    }
+
+    pub fn pop_min(&self) -> Option<(&T, Self)> {
+        match self.root {
+            LazyNode::Empty => None,
+            LazyNode::Tree(ref x, ref a, ref m) => {
+                Some((x, LazyPairingHeap{root: a.merge(m.eval()), size: self.size - 1}))
+            },
+        }
+    }
+
+    pub fn try_find_min(&self) -> Option<&T> {
+        match self.root {
+            LazyNode::Empty => None,
+            LazyNode::Tree(ref x, _, _) => Some(x),
+        }
+    }
+
+    pub fn try_delete_min(&self) -> Option<Self> {
+        self.pop_min().map(|(_, h)| h)
+    }
+
+    pub fn find_min(&self) -> &T {
+        self.try_find_min().expect("heap is empty!")
+    }
+
+    pub fn delete_min(&self) -> Self {
+        self.try_delete_min().expect("heap is empty!")
+    }
+
+    // Iterates elements in ascending order
+    pub fn iter(&self) -> traits::HeapIter<T, Self> {
+        traits::HeapIter::new(self.clone())
+    }
+
+    pub fn from_slice(a: &[T]) -> Self {
+        a.iter().fold(LazyPairingHeap::empty(), |h, x| h.insert(x.clone()))
+    }
+}
+
+impl<'a, T> traits::Heap<T> for LazyPairingHeap<'a, T>
+where T: 'a + Ord + Clone + Debug + Shareable {
+    fn empty() -> Self {
+        LazyPairingHeap::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn insert(&self, x: T) -> Self {
+        self.insert(x)
+    }
+
+    fn merge(&self, other: &Self) -> Self {
+        self.merge(other)
+    }
+
+    fn try_find_min(&self) -> Option<&T> {
+        self.try_find_min()
+    }
+
+    fn try_delete_min(&self) -> Option<Self> {
+        self.try_delete_min()
+    }
+
+    fn pop_min(&self) -> Option<(&T, Self)> {
+        self.pop_min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long() {
+        // Sorted insertion makes a root with n children, and reversed one makes a path of depth n
+        let n = 100000;
+        let h = PairingHeap::from_slice(&(0..n).collect::<Vec<_>>());
+        assert_eq!(h.len(), n as usize);
+        assert!(h.iter().eq(0..n));
+        let h = PairingHeap::from_slice(&(0..n).rev().collect::<Vec<_>>());
+        assert!(h.iter().eq(0..n));
+    }
+
+    #[test]
+    fn test_lazy_long() {
+        // Sorted insertion links a new suspension to the root every time, and reversed one makes a path
+        let n = 100000;
+        let h = LazyPairingHeap::from_slice(&(0..n).collect::<Vec<_>>());
+        assert_eq!(h.len(), n as usize);
+        assert!(h.iter().eq(0..n));
+        let h = LazyPairingHeap::from_slice(&(0..n).rev().collect::<Vec<_>>());
+        assert!(h.iter().eq(0..n));
+    }
+}
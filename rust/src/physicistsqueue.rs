@@ -0,0 +1,212 @@
+// Section 6.4.2
+//
+// Physicist's queue.
+// Amortized O(1) operations (enqueue/dequeue) even if old versions are used persistently
+
+use std::fmt::Debug;
+use lazy::Delayed;
+use list::List;
+use ptr::Shareable;
+use traits;
+
+// Note:
+// Invariants: |r| <= lenf and w is empty only when the front is empty
+// The front list is w followed by the suspended list f. The list is not evaluated incrementally
+// unlike streams, so w is a working copy to access the front element without forcing f. Rotation
+// suspends `f ++ reverse r` and f is forced only when w gets empty.
+// In the book, f is the whole front list and each dequeue suspends tail of f. Here dequeue takes
+// tail of w only, since nesting one suspension per dequeue makes forcing and dropping recursive as
+// deep as the number of dequeues. Only a rotation wraps the previous f, and the length of the front
+// doubles on each rotation, so at most O(log n) suspensions are nested.
+#[derive(Clone, Debug)]
+pub struct PhysicistsQueue<'a, T: 'a + Clone + Debug + Shareable> {
+    w: List<T>,
+    lenf: usize,
+    f: Delayed<'a, List<T>>,
+    lenr: usize,
+    r: List<T>,
+}
+
+impl<'a, T> PhysicistsQueue<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    pub fn empty() -> Self {
+        PhysicistsQueue {
+            w: List::empty(),
+            lenf: 0,
+            f: Delayed::constant(List::empty()),
+            lenr: 0,
+            r: List::empty(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lenf == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.lenf + self.lenr
+    }
+
+    fn checkw(w: List<T>, lenf: usize, f: Delayed<'a, List<T>>, lenr: usize, r: List<T>) -> Self {
+        if w.is_empty() {
+            PhysicistsQueue{w: f.eval().clone(), lenf, f: Delayed::constant(List::empty()), lenr, r}
+        } else {
+            PhysicistsQueue{w, lenf, f, lenr, r}
+        }
+    }
+
+    fn check(w: List<T>, lenf: usize, f: Delayed<'a, List<T>>, lenr: usize, r: List<T>) -> Self {
+        if lenr <= lenf {
+            PhysicistsQueue::checkw(w, lenf, f, lenr, r)
+        } else {
+            let f = lazily!{
+                f.eval().concat(&r.rev())
+            };
+            PhysicistsQueue::checkw(w, lenf + lenr, f, 0, List::empty())
+        }
+    }
+
+    pub fn enq(&self, x: T) -> Self {
+        PhysicistsQueue::check(self.w.clone(), self.lenf, self.f.clone(), self.lenr + 1, self.r.cons(x))
+    }
+
+    pub fn pop(&self) -> Option<(&T, Self)> {
+        self.w.uncons().map(|(x, w)| {
+            (x, PhysicistsQueue::check(w.clone(), self.lenf - 1, self.f.clone(), self.lenr, self.r.clone()))
+        })
+    }
+
+    pub fn try_top(&self) -> Option<&T> {
+        self.w.try_head()
+    }
+
+    pub fn try_deq(&self) -> Option<Self> {
+        self.pop().map(|(_, q)| q)
+    }
+
+    pub fn top(&self) -> &T {
+        self.try_top().expect("Queue is empty")
+    }
+
+    pub fn deq(&self) -> Self {
+        self.try_deq().expect("Queue is empty")
+    }
+}
+
+impl<'a, T> traits::Queue<T> for PhysicistsQueue<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn empty() -> Self {
+        PhysicistsQueue::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn enq(&self, x: T) -> Self {
+        self.enq(x)
+    }
+
+    fn try_top(&self) -> Option<&T> {
+        self.try_top()
+    }
+
+    fn try_deq(&self) -> Option<Self> {
+        self.try_deq()
+    }
+
+    fn pop(&self) -> Option<(&T, Self)> {
+        self.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use counted::{Counted, clones};
+    use queue::Queue;
+
+    #[test]
+    fn test_pop() {
+        let q = PhysicistsQueue::<i32>::empty();
+        assert!(q.is_empty());
+        assert!(q.pop().is_none());
+        let q = q.enq(1).enq(2).enq(3);
+        assert_eq!(q.len(), 3);
+        let (x, q) = q.pop().unwrap();
+        assert_eq!(x, &1);
+        let mut q = q.enq(4);
+        let mut v = vec![];
+        while let Some((x, rest)) = q.pop() {
+            v.push(*x);
+            q = rest;
+        }
+        assert_eq!(v, vec![2, 3, 4]);
+        assert!(q.is_empty());
+        assert_eq!(q.len(), 0);
+    }
+
+    #[test]
+    fn test_same_as_batched_queue() {
+        let mut q = PhysicistsQueue::empty();
+        let mut b = Queue::empty();
+        for i in 0..1000 {
+            q = q.enq(i);
+            b = b.enq(i);
+            if i % 3 == 0 {
+                assert_eq!(q.top(), b.top());
+                q = q.deq();
+                b = b.deq();
+            }
+        }
+        while !b.is_empty() {
+            assert_eq!(q.top(), b.top());
+            q = q.deq();
+            b = b.deq();
+        }
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn test_persistent_deq() {
+        // Dequeuing from an old version again reuses memoized suspensions. When w gets empty, the
+        // first dequeue forces the rotation and copies elements, but the following ones don't.
+        let n = 1000;
+        let m = 10;
+        let mut q = (0..n).fold(PhysicistsQueue::empty(), |q, i| q.enq(Counted(i)));
+        let mut forced = 0;
+        for i in 0..n {
+            if i + 1 < n {
+                let before = clones();
+                q.deq();
+                if clones() > before {
+                    assert_eq!(q.w.len(), 1, "f must be forced only when w gets empty");
+                    forced += 1;
+                }
+                let before = clones();
+                for _ in 0..m {
+                    assert_eq!(q.deq().top(), &Counted(i + 1));
+                }
+                assert_eq!(clones() - before, 0);
+            }
+            q = q.deq();
+        }
+        assert!(q.is_empty());
+        // w got empty twice: after the first element and after the elements forced then
+        assert_eq!(forced, 2);
+    }
+
+    #[test]
+    fn test_long() {
+        let n = 1000000;
+        let q = (0..n).fold(PhysicistsQueue::empty(), |q, i| q.enq(i));
+        let half = (0..n / 2).fold(q.clone(), |q, _| q.deq());
+        assert_eq!(half.top(), &(n / 2));
+        drop(half);
+        let q = (0..n).fold(q, |q, i| {
+            assert_eq!(q.top(), &i);
+            q.deq()
+        });
+        assert!(q.is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+// Reference counted pointer shared by all persistent data structures in this crate
+//
+// Rc is used by default. When `sync` feature is enabled, Arc is used instead so that snapshots of
+// data structures can be shared across threads.
+
+#[cfg(not(feature = "sync"))]
+pub use std::rc::Rc as Ptr;
+
+#[cfg(feature = "sync")]
+pub use std::sync::Arc as Ptr;
+
+// Additional bound on elements of lazily evaluated structures. Delayed computations capture their
+// elements, so they must be Send and Sync to share them across threads when `sync` feature is enabled.
+#[cfg(not(feature = "sync"))]
+pub trait Shareable {}
+#[cfg(not(feature = "sync"))]
+impl<T> Shareable for T {}
+
+#[cfg(feature = "sync")]
+pub trait Shareable: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync> Shareable for T {}
+
+#[cfg(all(test, feature = "sync"))]
+mod tests {
+    use std::thread;
+    use list::List;
+    use catlist::CatList;
+    use binrandomaccesslist::BinRandomAccessList;
+    use skewbinrandomaccesslist::SkewBinRandomAccessList;
+    use queue::Queue;
+    use bankersqueue::BankersQueue;
+    use physicistsqueue::PhysicistsQueue;
+    use realtimequeue::RealTimeQueue;
+    use hoodmelvillequeue::HoodMelvilleQueue;
+    use deque::Deque;
+    use bankersdeque::BankersDeque;
+    use realtimedeque::RealTimeDeque;
+    use catdeque::{SimpleCatDeque, CatDeque};
+    use rbtree::RBTree;
+    use leftheap::LeftHeap;
+    use binheap::BinHeap;
+    use skewbinheap::SkewBinHeap;
+    use bootstrappedheap::BootstrappedHeap;
+    use splayheap::SplayHeap;
+    use pairingheap::{PairingHeap, LazyPairingHeap};
+    use weightleftheap::WeightLeftHeap;
+    use indexedheap::IndexedHeap;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<List<i32>>();
+        assert_send_sync::<CatList<i32>>();
+        assert_send_sync::<BinRandomAccessList<i32>>();
+        assert_send_sync::<SkewBinRandomAccessList<i32>>();
+        assert_send_sync::<Queue<i32>>();
+        assert_send_sync::<BankersQueue<i32>>();
+        assert_send_sync::<PhysicistsQueue<i32>>();
+        assert_send_sync::<RealTimeQueue<i32>>();
+        assert_send_sync::<HoodMelvilleQueue<i32>>();
+        assert_send_sync::<Deque<i32>>();
+        assert_send_sync::<BankersDeque<i32>>();
+        assert_send_sync::<RealTimeDeque<i32>>();
+        assert_send_sync::<SimpleCatDeque<i32>>();
+        assert_send_sync::<CatDeque<i32>>();
+        assert_send_sync::<RBTree<i32>>();
+        assert_send_sync::<LeftHeap<i32>>();
+        assert_send_sync::<BinHeap<i32>>();
+        assert_send_sync::<SkewBinHeap<i32>>();
+        assert_send_sync::<BootstrappedHeap<i32>>();
+        assert_send_sync::<SplayHeap<i32>>();
+        assert_send_sync::<PairingHeap<i32>>();
+        assert_send_sync::<LazyPairingHeap<i32>>();
+        assert_send_sync::<WeightLeftHeap<i32>>();
+        assert_send_sync::<IndexedHeap<i32, i32>>();
+    }
+
+    #[test]
+    fn test_share_snapshot() {
+        let t = (0..100).fold(RBTree::empty(), |t, i| t.insert(i));
+        let q = (0..100).fold(Queue::empty(), |q, i| q.enq(i));
+        let handles = (0..4).map(|n| {
+            let t = t.clone();
+            let q = q.clone();
+            thread::spawn(move || {
+                // Each thread makes its own version from the shared snapshot
+                let t = t.insert(100 + n);
+                let q = q.deq().enq(100 + n);
+                assert!(t.member(&(100 + n)));
+                assert_eq!(q.top(), &1);
+            })
+        }).collect::<Vec<_>>();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert!(!t.member(&100));
+        assert_eq!(q.top(), &0);
+    }
+}
@@ -5,54 +5,143 @@
 
 use std::fmt::Debug;
 use list::{List, Node};
+use traits;
+
+pub use list::FrontBackIter as Iter;
 
 // Note:
 // Invariants: When f is empty, r must be empty also.
+// Lengths of f and r are cached since List::len is O(n).
 #[derive(Clone, Debug)]
 pub struct Queue<T: Clone + Debug> {
     f: List<T>,
+    lenf: usize,
     r: List<T>,
+    lenr: usize,
 }
 
 impl<T> Queue<T>
 where T: Clone + Debug {
     pub fn empty() -> Self {
-        Queue{f: List::empty(), r: List::empty()}
+        Queue{f: List::empty(), lenf: 0, r: List::empty(), lenr: 0}
     }
 
     pub fn is_empty(&self) -> bool {
         self.f.is_empty()
     }
 
-    fn check(f: List<T>, r: List<T>) -> Self {
+    pub fn len(&self) -> usize {
+        self.lenf + self.lenr
+    }
+
+    fn check(f: List<T>, lenf: usize, r: List<T>, lenr: usize) -> Self {
         if !f.is_empty() {
-            Queue{f, r}
+            Queue{f, lenf, r, lenr}
         } else {
             Queue {
                 f: r.rev(),
+                lenf: lenr,
                 r: List::empty(),
+                lenr: 0,
             }
         }
     }
 
     pub fn enq(&self, x: T) -> Self {
-        Queue::check(self.f.clone(), self.r.cons(x))
+        Queue::check(self.f.clone(), self.lenf, self.r.cons(x), self.lenr + 1)
     }
 
-    pub fn top(&self) -> &T {
-        if self.f.is_empty() {
-            panic!("Queue is empty")
-        } else {
-            self.f.head()
+    pub fn pop(&self) -> Option<(&T, Self)> {
+        match *self.f.root() {
+            Node::Nil => None,
+            Node::Cons(ref x, ref xs) => {
+                Some((x, Queue::check(xs.clone(), self.lenf - 1, self.r.clone(), self.lenr)))
+            },
         }
     }
 
+    pub fn try_top(&self) -> Option<&T> {
+        self.f.try_head()
+    }
+
+    pub fn try_deq(&self) -> Option<Self> {
+        self.pop().map(|(_, q)| q)
+    }
+
+    pub fn top(&self) -> &T {
+        self.try_top().expect("Queue is empty")
+    }
+
     pub fn deq(&self) -> Self {
-        match self.f.root() {
-            &Node::Nil => panic!("Queue is empty"),
-            &Node::Cons(_, ref xs) => Queue::check(xs.clone(), self.r.clone()),
+        self.try_deq().expect("Queue is empty")
+    }
+
+    // Iterates elements from front (the oldest) to back (the newest)
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter::new(&self.f, &self.r)
+    }
+
+    // Iterates elements from back (the newest) to front (the oldest)
+    pub fn iter_rev(&self) -> Iter<'_, T> {
+        Iter::new(&self.r, &self.f)
+    }
+
+    // `idx`-th element from front. O(idx) when the element is in f and O(n) otherwise.
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        if idx < self.lenf {
+            self.f.iter().nth(idx)
+        } else if idx < self.len() {
+            self.r.iter().nth(self.len() - 1 - idx)
+        } else {
+            None
         }
     }
+
+    pub fn contains(&self, x: &T) -> bool
+    where T: PartialEq {
+        self.f.iter().chain(self.r.iter()).any(|y| y == x)
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Queue<T>
+where T: Clone + Debug {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> traits::Queue<T> for Queue<T>
+where T: Clone + Debug {
+    fn empty() -> Self {
+        Queue::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn enq(&self, x: T) -> Self {
+        self.enq(x)
+    }
+
+    fn try_top(&self) -> Option<&T> {
+        self.try_top()
+    }
+
+    fn try_deq(&self) -> Option<Self> {
+        self.try_deq()
+    }
+
+    fn pop(&self) -> Option<(&T, Self)> {
+        self.pop()
+    }
 }
 
 #[cfg(test)]
@@ -73,6 +162,20 @@ mod tests {
         assert_eq!(q.top(), &1);
     }
 
+    #[test]
+    fn test_pop() {
+        let q = Queue::<i32>::empty();
+        assert!(q.try_top().is_none());
+        assert!(q.try_deq().is_none());
+        assert!(q.pop().is_none());
+        let q = q.enq(1).enq(2);
+        let (x, q) = q.pop().unwrap();
+        assert_eq!(x, &1);
+        let (x, q) = q.pop().unwrap();
+        assert_eq!(x, &2);
+        assert!(q.pop().is_none());
+    }
+
     #[test]
     fn test_invariants() {
         let q = Queue::empty().enq(1).enq(2).enq(3);
@@ -83,4 +186,45 @@ mod tests {
         let q = q.deq();
         assert!(q.is_empty());
     }
+
+    #[test]
+    fn test_len() {
+        let q = Queue::empty();
+        assert_eq!(q.len(), 0);
+        let q = q.enq(1).enq(2).enq(3);
+        assert_eq!(q.len(), 3);
+        let q = q.deq();
+        assert_eq!(q.len(), 2);
+        let q = q.enq(4).deq().deq().deq();
+        assert_eq!(q.len(), 0);
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn test_iter() {
+        // f = [1, 2] and r = [4, 3]
+        let q = Queue::empty().enq(0).enq(1).enq(2).deq().enq(3).enq(4);
+        assert_eq!(q.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(q.iter_rev().cloned().collect::<Vec<_>>(), vec![4, 3, 2, 1]);
+        assert_eq!(q.to_vec(), vec![1, 2, 3, 4]);
+        let mut v = vec![];
+        for x in &q {
+            v.push(*x);
+        }
+        assert_eq!(v, vec![1, 2, 3, 4]);
+        assert!(Queue::<i32>::empty().iter().next().is_none());
+        assert!(Queue::<i32>::empty().to_vec().is_empty());
+    }
+
+    #[test]
+    fn test_get_contains() {
+        let q = Queue::empty().enq(0).enq(1).enq(2).deq().enq(3).enq(4);
+        for i in 0..4 {
+            assert_eq!(q.get(i), Some(&(i as i32 + 1)));
+            assert!(q.contains(&(i as i32 + 1)));
+        }
+        assert_eq!(q.get(4), None);
+        assert!(!q.contains(&0));
+        assert!(!q.contains(&5));
+    }
 }
@@ -3,8 +3,11 @@
 // Red-Black tree
 
 use std::fmt::Debug;
-use std::rc::Rc;
-use std::cmp::PartialOrd;
+use std::cmp::{PartialOrd, Ordering};
+use std::borrow::Borrow;
+use std::ops::{Bound, RangeBounds};
+use ptr::Ptr;
+use traits;
 
 #[derive(Clone, Debug, PartialEq)]
 enum Color {Red, Black}
@@ -17,36 +20,59 @@ enum Node<T: Clone + PartialOrd + Debug> {
         left: Link<T>,
         right: Link<T>,
         val: T,
+        size: usize, // Number of elements in this subtree for order statistics
+        bh: usize, // Black height (black nodes on the left spine) for join
     },
 }
 
 impl<T> Node<T>
 where T: Clone + PartialOrd + Debug {
-    fn member(&self, v: &T) -> bool {
+    fn size(&self) -> usize {
         match *self {
-            Node::Leaf => false,
-            Node::Knot{color: _, ref left, ref right, ref val} => {
-                if val < v {
-                    right.member(v)
-                } else if v < val {
-                    left.member(v)
+            Node::Leaf => 0,
+            Node::Knot{size, ..} => size,
+        }
+    }
+
+    fn black_height(&self) -> usize {
+        match *self {
+            Node::Leaf => 0,
+            Node::Knot{bh, ..} => bh,
+        }
+    }
+
+    // Like std's BTreeSet, elements can be looked up by any borrowed form of them.
+    fn find<Q>(&self, v: &Q) -> Option<&T>
+    where T: Borrow<Q>, Q: PartialOrd + ?Sized {
+        match *self {
+            Node::Leaf => None,
+            Node::Knot{color: _, ref left, ref right, ref val, ..} => {
+                if val.borrow() < v {
+                    right.find(v)
+                } else if v < val.borrow() {
+                    left.find(v)
                 } else {
                     // val == v
-                    true
+                    Some(val)
                 }
             }
         }
     }
 }
 
+fn knot<T: Clone + PartialOrd + Debug>(color: Color, left: Link<T>, right: Link<T>, val: T) -> Link<T> {
+    let size = left.size() + right.size() + 1;
+    let bh = left.black_height() + if color == Color::Black { 1 } else { 0 };
+    Ptr::new(Node::Knot{color, left, right, val, size, bh})
+}
 fn black<T: Clone + PartialOrd + Debug>(left: Link<T>, right: Link<T>, val: T) -> Link<T> {
-    Rc::new(Node::Knot{color: Color::Black, left, right, val})
+    knot(Color::Black, left, right, val)
 }
 fn red<T: Clone + PartialOrd + Debug>(left: Link<T>, right: Link<T>, val: T) -> Link<T> {
-    Rc::new(Node::Knot{color: Color::Red, left, right, val})
+    knot(Color::Red, left, right, val)
 }
 
-type Link<T> = Rc<Node<T>>;
+type Link<T> = Ptr<Node<T>>;
 
 #[derive(Clone, Debug)]
 pub struct RBTree<T: Clone + PartialOrd + Debug> {
@@ -56,47 +82,59 @@ pub struct RBTree<T: Clone + PartialOrd + Debug> {
 impl<T> RBTree<T>
 where T: Clone + PartialOrd + Debug {
     pub fn empty() -> Self {
-        RBTree{root: Rc::new(Node::Leaf)}
+        RBTree{root: Ptr::new(Node::Leaf)}
     }
 
     pub fn is_empty(&self) -> bool {
         *self.root == Node::Leaf
     }
 
-    pub fn member(&self, v: &T) -> bool {
-        self.root.member(v)
+    pub fn len(&self) -> usize {
+        self.root.size()
+    }
+
+    pub fn member<Q>(&self, v: &Q) -> bool
+    where T: Borrow<Q>, Q: PartialOrd + ?Sized {
+        self.root.find(v).is_some()
+    }
+
+    pub fn get<Q>(&self, v: &Q) -> Option<&T>
+    where T: Borrow<Q>, Q: PartialOrd + ?Sized {
+        self.root.find(v)
     }
 
     // exercise 3.10: Eliminate redundant comparison
     fn balance(color: Color, left: Link<T>, right: Link<T>, val: T) -> Link<T> {
         if color == Color::Red {
-            return Rc::new(Node::Knot{color, left, right, val});
+            return knot(color, left, right, val);
         }
 
-        if let Node::Knot{color: Color::Red, left: ref l1, right: ref r1, val: ref v1} = *left {
-            if let Node::Knot{color: Color::Red, left: ref l2, right: ref r2, val: ref v2} = **l1 {
+        if let Node::Knot{color: Color::Red, left: ref l1, right: ref r1, val: ref v1, ..} = *left {
+            if let Node::Knot{color: Color::Red, left: ref l2, right: ref r2, val: ref v2, ..} = **l1 {
                 return red(
                     black(l2.clone(), r2.clone(), v2.clone()),
                     black(r1.clone(), right, val),
                     v1.clone(),
                 );
             }
-            if let Node::Knot{color: Color::Red, left: ref l2, right: ref r2, val: ref v2} = **r1 {
+            if let Node::Knot{color: Color::Red, left: ref l2, right: ref r2, val: ref v2, ..} = **r1 {
                 return red(
                     black(l1.clone(), l2.clone(), v1.clone()),
                     black(r2.clone(), right, val),
                     v2.clone(),
                 );
             }
-        } if let Node::Knot{color: Color::Red, left: ref l1, right: ref r1, val: ref v1} = *right {
-            if let Node::Knot{color: Color::Red, left: ref l2, right: ref r2, val: ref v2} = **l1 {
+        }
+
+        if let Node::Knot{color: Color::Red, left: ref l1, right: ref r1, val: ref v1, ..} = *right {
+            if let Node::Knot{color: Color::Red, left: ref l2, right: ref r2, val: ref v2, ..} = **l1 {
                 return red(
                     black(left, l2.clone(), val),
                     black(r2.clone(), r1.clone(), v1.clone()),
                     v2.clone(),
                 );
             }
-            if let Node::Knot{color: Color::Red, left: ref l2, right: ref r2, val: ref v2} = **r1 {
+            if let Node::Knot{color: Color::Red, left: ref l2, right: ref r2, val: ref v2, ..} = **r1 {
                 return red(
                     black(left, l1.clone(), val),
                     black(l2.clone(), r2.clone(), v2.clone()),
@@ -105,24 +143,28 @@ where T: Clone + PartialOrd + Debug {
             }
         }
 
-        return Rc::new(Node::Knot{color, left, right, val});
+        knot(color, left, right, val)
     }
 
-    fn ins(link: &Link<T>, x: T) -> Link<T> {
+    // When `replace` is true, an element equivalent to `x` is replaced with `x`. Otherwise the tree
+    // is shared as-is.
+    fn ins(link: &Link<T>, x: T, replace: bool) -> Link<T> {
         match **link {
             Node::Leaf => {
-                let e = Rc::new(Node::Leaf);
+                let e = Ptr::new(Node::Leaf);
                 red(e.clone(), e, x)
             },
-            Node::Knot{ref color, ref left, ref right, ref val} => {
+            Node::Knot{ref color, ref left, ref right, ref val, ..} => {
                 let color = color.clone();
                 let val = val.clone();
                 if x < val {
-                    let left = RBTree::ins(left, x);
+                    let left = RBTree::ins(left, x, replace);
                     RBTree::balance(color, left, right.clone(), val)
                 } else if val < x {
-                    let right = RBTree::ins(right, x);
+                    let right = RBTree::ins(right, x, replace);
                     RBTree::balance(color, left.clone(), right, val)
+                } else if replace {
+                    knot(color, left.clone(), right.clone(), x)
                 } else {
                     link.clone()
                 }
@@ -130,41 +172,660 @@ where T: Clone + PartialOrd + Debug {
         }
     }
 
-    pub fn insert(&self, v: T) -> Self {
-        match *RBTree::ins(&self.root, v) {
-            Node::Knot{color: _, left: ref l, right: ref r, val: ref v} => {
+    fn insert_impl(&self, v: T, replace: bool) -> Self {
+        match *RBTree::ins(&self.root, v, replace) {
+            Node::Knot{color: _, left: ref l, right: ref r, val: ref v, ..} => {
                 RBTree{root: black(l.clone(), r.clone(), v.clone())}
             },
             Node::Leaf => unreachable!(),
         }
     }
 
-    fn link_from_sorted(idx: usize, slice: &[T]) -> (Link<T>, usize) {
-        match slice.get(idx) {
-            None => (Rc::new(Node::Leaf), idx),
-            Some(val) => {
-                let (left, idx) = RBTree::link_from_sorted(idx + 1, slice);
-                let (right, idx) = RBTree::link_from_sorted(idx + 1, slice);
-                let color = match &*left {
-                    &Node::Leaf => Color::Red,
-                    &Node::Knot{color: Color::Red, ..} => Color::Black,
-                    &Node::Knot{color: Color::Black, ..} => Color::Red,
-                };
-                (Rc::new(Node::Knot{color, left, right, val: val.clone()}), idx)
-            }
+    pub fn insert(&self, v: T) -> Self {
+        self.insert_impl(v, false)
+    }
+
+    // Iterates elements in sorted order
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut it = Iter{stack: vec![]};
+        it.push_lefts(&self.root);
+        it
+    }
+
+    fn make_red(link: &Link<T>) -> Link<T> {
+        match **link {
+            Node::Knot{ref left, ref right, ref val, ..} => red(left.clone(), right.clone(), val.clone()),
+            Node::Leaf => link.clone(),
+        }
+    }
+
+    fn make_black(link: &Link<T>) -> Link<T> {
+        match **link {
+            Node::Knot{ref left, ref right, ref val, ..} => black(left.clone(), right.clone(), val.clone()),
+            Node::Leaf => link.clone(),
+        }
+    }
+
+    fn is_black(link: &Link<T>) -> bool {
+        matches!(**link, Node::Knot{color: Color::Black, ..})
+    }
+
+    // Deletion follows Kahrs' algorithm (as verified in Coq's MSetRBT). Deleting from a black node
+    // returns a tree whose black height is decreased by one and whose root may be red with a red child.
+    // bal_left and bal_right restore the invariants when the left or right subtree is such a tree.
+    fn bal_left(left: Link<T>, right: Link<T>, val: T) -> Link<T> {
+        if let Node::Knot{color: Color::Red, left: ref a, right: ref b, val: ref x, ..} = *left {
+            return red(black(a.clone(), b.clone(), x.clone()), right, val);
+        }
+        match *right {
+            Node::Knot{color: Color::Black, left: ref a, right: ref b, val: ref y, ..} => {
+                RBTree::balance(Color::Black, left, red(a.clone(), b.clone(), y.clone()), val)
+            },
+            Node::Knot{color: Color::Red, left: ref l1, right: ref c, val: ref z, ..} => {
+                match **l1 {
+                    Node::Knot{color: Color::Black, left: ref a, right: ref b, val: ref y, ..} => red(
+                        black(left, a.clone(), val),
+                        RBTree::balance(Color::Black, b.clone(), RBTree::make_red(c), z.clone()),
+                        y.clone(),
+                    ),
+                    _ => unreachable!("red-black tree invariant is broken"),
+                }
+            },
+            Node::Leaf => unreachable!("red-black tree invariant is broken"),
+        }
+    }
+
+    fn bal_right(left: Link<T>, right: Link<T>, val: T) -> Link<T> {
+        if let Node::Knot{color: Color::Red, left: ref b, right: ref c, val: ref y, ..} = *right {
+            return red(left, black(b.clone(), c.clone(), y.clone()), val);
+        }
+        match *left {
+            Node::Knot{color: Color::Black, left: ref a, right: ref b, val: ref x, ..} => {
+                RBTree::balance(Color::Black, red(a.clone(), b.clone(), x.clone()), right, val)
+            },
+            Node::Knot{color: Color::Red, left: ref a, right: ref r1, val: ref x, ..} => {
+                match **r1 {
+                    Node::Knot{color: Color::Black, left: ref b, right: ref c, val: ref y, ..} => red(
+                        RBTree::balance(Color::Black, RBTree::make_red(a), b.clone(), x.clone()),
+                        black(c.clone(), right, val),
+                        y.clone(),
+                    ),
+                    _ => unreachable!("red-black tree invariant is broken"),
+                }
+            },
+            Node::Leaf => unreachable!("red-black tree invariant is broken"),
+        }
+    }
+
+    // Concatenates two trees whose black heights are the same. All elements of `l` are less than
+    // elements of `r`.
+    fn append(l: &Link<T>, r: &Link<T>) -> Link<T> {
+        match (&**l, &**r) {
+            (&Node::Leaf, _) => r.clone(),
+            (_, &Node::Leaf) => l.clone(),
+            (Node::Knot{color: Color::Red, left: ll, right: lr, val: lx, ..},
+             Node::Knot{color: Color::Red, left: rl, right: rr, val: rx, ..}) => {
+                let m = RBTree::append(lr, rl);
+                match *m {
+                    Node::Knot{color: Color::Red, left: ref ml, right: ref mr, val: ref mx, ..} => red(
+                        red(ll.clone(), ml.clone(), lx.clone()),
+                        red(mr.clone(), rr.clone(), rx.clone()),
+                        mx.clone(),
+                    ),
+                    _ => red(ll.clone(), red(m, rr.clone(), rx.clone()), lx.clone()),
+                }
+            },
+            (Node::Knot{color: Color::Black, left: ll, right: lr, val: lx, ..},
+             Node::Knot{color: Color::Black, left: rl, right: rr, val: rx, ..}) => {
+                let m = RBTree::append(lr, rl);
+                match *m {
+                    Node::Knot{color: Color::Red, left: ref ml, right: ref mr, val: ref mx, ..} => red(
+                        black(ll.clone(), ml.clone(), lx.clone()),
+                        black(mr.clone(), rr.clone(), rx.clone()),
+                        mx.clone(),
+                    ),
+                    _ => RBTree::bal_left(ll.clone(), black(m, rr.clone(), rx.clone()), lx.clone()),
+                }
+            },
+            (_, Node::Knot{color: Color::Red, left: rl, right: rr, val: rx, ..}) => {
+                red(RBTree::append(l, rl), rr.clone(), rx.clone())
+            },
+            (Node::Knot{color: Color::Red, left: ll, right: lr, val: lx, ..}, _) => {
+                red(ll.clone(), RBTree::append(lr, r), lx.clone())
+            },
+        }
+    }
+
+    fn del<Q>(link: &Link<T>, x: &Q) -> Link<T>
+    where T: Borrow<Q>, Q: PartialOrd + ?Sized {
+        match **link {
+            Node::Leaf => link.clone(),
+            Node::Knot{ref left, ref right, ref val, ..} => {
+                if x < val.borrow() {
+                    let l = RBTree::del(left, x);
+                    if RBTree::is_black(left) {
+                        RBTree::bal_left(l, right.clone(), val.clone())
+                    } else {
+                        red(l, right.clone(), val.clone())
+                    }
+                } else if val.borrow() < x {
+                    let r = RBTree::del(right, x);
+                    if RBTree::is_black(right) {
+                        RBTree::bal_right(left.clone(), r, val.clone())
+                    } else {
+                        red(left.clone(), r, val.clone())
+                    }
+                } else {
+                    RBTree::append(left, right)
+                }
+            },
+        }
+    }
+
+    pub fn remove<Q>(&self, v: &Q) -> Self
+    where T: Borrow<Q>, Q: PartialOrd + ?Sized {
+        // When the tree does not have the value, the tree can be shared without copying any node.
+        if !self.member(v) {
+            return self.clone();
+        }
+        RBTree{root: RBTree::make_black(&RBTree::del(&self.root, v))}
+    }
+
+    // Nodes at the deepest level are red only when the level is not filled completely. Since each
+    // subtree is split at the middle, the other levels are always filled and their nodes are black.
+    fn link_from_sorted(slice: &[T], depth: u32, red_depth: u32) -> Link<T> {
+        if slice.is_empty() {
+            return Ptr::new(Node::Leaf);
         }
+        let mid = slice.len() / 2;
+        let left = RBTree::link_from_sorted(&slice[..mid], depth + 1, red_depth);
+        let right = RBTree::link_from_sorted(&slice[mid + 1..], depth + 1, red_depth);
+        let color = if depth == red_depth { Color::Red } else { Color::Black };
+        knot(color, left, right, slice[mid].clone())
     }
 
     // exercise 3.9: Create RBTree from ordered unique list
     pub fn from_sorted(slice: &[T]) -> Self {
-        let (root, _) = RBTree::link_from_sorted(0, slice);
-        RBTree{root}
+        // Levels 0..red_depth are filled completely.
+        let red_depth = (slice.len() + 1).ilog2();
+        RBTree{root: RBTree::link_from_sorted(slice, 0, red_depth)}
+    }
+
+    pub fn min(&self) -> Option<&T> {
+        let mut node = &self.root;
+        let mut min = None;
+        while let Node::Knot{ref left, ref val, ..} = **node {
+            min = Some(val);
+            node = left;
+        }
+        min
+    }
+
+    pub fn max(&self) -> Option<&T> {
+        let mut node = &self.root;
+        let mut max = None;
+        while let Node::Knot{ref right, ref val, ..} = **node {
+            max = Some(val);
+            node = right;
+        }
+        max
+    }
+
+    // The greatest element which is less than `v`
+    pub fn predecessor<Q>(&self, v: &Q) -> Option<&T>
+    where T: Borrow<Q>, Q: PartialOrd + ?Sized {
+        let mut node = &self.root;
+        let mut found = None;
+        while let Node::Knot{ref left, ref right, ref val, ..} = **node {
+            if val.borrow() < v {
+                found = Some(val);
+                node = right;
+            } else {
+                node = left;
+            }
+        }
+        found
+    }
+
+    // The least element which is greater than `v`
+    pub fn successor<Q>(&self, v: &Q) -> Option<&T>
+    where T: Borrow<Q>, Q: PartialOrd + ?Sized {
+        let mut node = &self.root;
+        let mut found = None;
+        while let Node::Knot{ref left, ref right, ref val, ..} = **node {
+            if v < val.borrow() {
+                found = Some(val);
+                node = left;
+            } else {
+                node = right;
+            }
+        }
+        found
+    }
+
+    // Number of elements which are less than `v`
+    pub fn rank<Q>(&self, v: &Q) -> usize
+    where T: Borrow<Q>, Q: PartialOrd + ?Sized {
+        let mut node = &self.root;
+        let mut rank = 0;
+        while let Node::Knot{ref left, ref right, ref val, ..} = **node {
+            if val.borrow() < v {
+                rank += left.size() + 1;
+                node = right;
+            } else {
+                node = left;
+            }
+        }
+        rank
+    }
+
+    // `idx`-th least element (0-origin)
+    pub fn select(&self, idx: usize) -> Option<&T> {
+        let mut node = &self.root;
+        let mut idx = idx;
+        while let Node::Knot{ref left, ref right, ref val, ..} = **node {
+            let size = left.size();
+            if idx < size {
+                node = left;
+            } else if idx == size {
+                return Some(val);
+            } else {
+                idx -= size + 1;
+                node = right;
+            }
+        }
+        None
+    }
+
+    // Iterates elements in the range in sorted order
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> Range<'_, T> {
+        let mut iter = Iter{stack: vec![]};
+        let mut node = &*self.root;
+        // Push the path to the least element in the range. Nodes on the path whose elements are
+        // below the range are skipped since all elements in their left subtrees are below as well.
+        while let Node::Knot{ref left, ref right, ref val, ..} = *node {
+            let above_start = match range.start_bound() {
+                Bound::Included(start) => start <= val,
+                Bound::Excluded(start) => start < val,
+                Bound::Unbounded => true,
+            };
+            if above_start {
+                iter.stack.push(node);
+                node = left;
+            } else {
+                node = right;
+            }
+        }
+        Range{iter, end: range.end_bound().cloned()}
+    }
+
+    // Descends the right spine of `l` until a black node whose black height is the same as `r`, puts
+    // `val` and `r` there as a red node, then rebalances red-red violations on the way back with
+    // `balance`. The root of the result may be red with a red child.
+    fn join_right(l: &Link<T>, lh: usize, val: T, r: &Link<T>, rh: usize) -> Link<T> {
+        match **l {
+            Node::Knot{ref color, ref left, ref right, val: ref v, ..} if *color == Color::Red || lh > rh => {
+                let h = if *color == Color::Black { lh - 1 } else { lh };
+                RBTree::balance(color.clone(), left.clone(), RBTree::join_right(right, h, val, r, rh), v.clone())
+            },
+            _ => red(l.clone(), r.clone(), val),
+        }
+    }
+
+    fn join_left(l: &Link<T>, lh: usize, val: T, r: &Link<T>, rh: usize) -> Link<T> {
+        match **r {
+            Node::Knot{ref color, ref left, ref right, val: ref v, ..} if *color == Color::Red || rh > lh => {
+                let h = if *color == Color::Black { rh - 1 } else { rh };
+                RBTree::balance(color.clone(), RBTree::join_left(l, lh, val, left, h), right.clone(), v.clone())
+            },
+            _ => red(l.clone(), r.clone(), val),
+        }
+    }
+
+    // All elements of `l` are less than `val` and all elements of `r` are greater than `val`.
+    // O(|bh(l) - bh(r)|) where bh is black height, which is cached in nodes.
+    fn join_link(l: &Link<T>, val: T, r: &Link<T>) -> Link<T> {
+        let (lh, rh) = (l.black_height(), r.black_height());
+        if lh > rh {
+            RBTree::make_black(&RBTree::join_right(l, lh, val, r, rh))
+        } else if lh < rh {
+            RBTree::make_black(&RBTree::join_left(l, lh, val, r, rh))
+        } else if RBTree::is_red(l) || RBTree::is_red(r) {
+            black(l.clone(), r.clone(), val)
+        } else {
+            red(l.clone(), r.clone(), val)
+        }
+    }
+
+    fn is_red(link: &Link<T>) -> bool {
+        matches!(**link, Node::Knot{color: Color::Red, ..})
+    }
+
+    // Concatenates two trees without a middle element. The least element of `r` is moved to the
+    // middle instead.
+    fn join2_link(l: &Link<T>, r: &Link<T>) -> Link<T> {
+        let rt = RBTree{root: r.clone()};
+        match rt.min() {
+            None => l.clone(),
+            Some(m) => RBTree::join_link(l, m.clone(), &RBTree::make_black(&RBTree::del(r, m))),
+        }
+    }
+
+    // When a side of the split child is the child itself, the original node is returned as the side
+    // instead of joining it again. So splitting outside of a subtree keeps the subtree shared.
+    fn split_link<Q>(link: &Link<T>, k: &Q) -> (Link<T>, bool, Link<T>)
+    where T: Borrow<Q>, Q: PartialOrd + ?Sized {
+        match **link {
+            Node::Leaf => (link.clone(), false, link.clone()),
+            Node::Knot{ref left, ref right, ref val, ..} => {
+                if k < val.borrow() {
+                    let (ll, found, lr) = RBTree::split_link(left, k);
+                    if Ptr::ptr_eq(&lr, left) {
+                        return (ll, found, link.clone());
+                    }
+                    (ll, found, RBTree::join_link(&lr, val.clone(), right))
+                } else if val.borrow() < k {
+                    let (rl, found, rr) = RBTree::split_link(right, k);
+                    if Ptr::ptr_eq(&rl, right) {
+                        return (link.clone(), found, rr);
+                    }
+                    (RBTree::join_link(left, val.clone(), &rl), found, rr)
+                } else {
+                    (left.clone(), true, right.clone())
+                }
+            },
+        }
+    }
+
+    // Splits the tree into a tree of elements less than `k` and a tree of elements greater than `k`.
+    // The boolean value is true when the tree has an element equivalent to `k`. O(log n)
+    pub fn split<Q>(&self, k: &Q) -> (Self, bool, Self)
+    where T: Borrow<Q>, Q: PartialOrd + ?Sized {
+        let (l, found, r) = RBTree::split_link(&self.root, k);
+        (RBTree{root: l}, found, RBTree{root: r})
+    }
+
+    // All elements of `l` must be less than `val` and all elements of `r` must be greater than `val`
+    pub fn join(l: &Self, val: T, r: &Self) -> Self {
+        RBTree{root: RBTree::make_black(&RBTree::join_link(&l.root, val, &r.root))}
+    }
+
+    // Set operations are join-based algorithms (Blelloch, Ferizovic and Sun, "Just Join for Parallel
+    // Ordered Sets"). They take O(m log(n/m + 1)) where m <= n. When both arguments are the same node,
+    // the result is known without visiting the subtree. Since `split` returns untouched subtrees as
+    // they are, operations on two versions of a tree visit only around the paths where they differ.
+    fn union_link(a: &Link<T>, b: &Link<T>) -> Link<T> {
+        if Ptr::ptr_eq(a, b) {
+            return a.clone();
+        }
+        match (&**a, &**b) {
+            (Node::Leaf, _) => b.clone(),
+            (_, Node::Leaf) => a.clone(),
+            (Node::Knot{left, right, val, ..}, _) => {
+                let (bl, _, br) = RBTree::split_link(b, val);
+                let l = RBTree::union_link(left, &bl);
+                let r = RBTree::union_link(right, &br);
+                RBTree::join_link(&l, val.clone(), &r)
+            },
+        }
+    }
+
+    fn intersection_link(a: &Link<T>, b: &Link<T>) -> Link<T> {
+        if Ptr::ptr_eq(a, b) {
+            return a.clone();
+        }
+        match (&**a, &**b) {
+            (Node::Leaf, _) => a.clone(),
+            (_, Node::Leaf) => b.clone(),
+            (Node::Knot{left, right, val, ..}, _) => {
+                let (bl, found, br) = RBTree::split_link(b, val);
+                let l = RBTree::intersection_link(left, &bl);
+                let r = RBTree::intersection_link(right, &br);
+                if found {
+                    RBTree::join_link(&l, val.clone(), &r)
+                } else {
+                    RBTree::join2_link(&l, &r)
+                }
+            },
+        }
+    }
+
+    fn difference_link(a: &Link<T>, b: &Link<T>) -> Link<T> {
+        if Ptr::ptr_eq(a, b) {
+            return Ptr::new(Node::Leaf);
+        }
+        match (&**a, &**b) {
+            (Node::Leaf, _) | (_, Node::Leaf) => a.clone(),
+            (_, Node::Knot{left, right, val, ..}) => {
+                let (al, _, ar) = RBTree::split_link(a, val);
+                let l = RBTree::difference_link(&al, left);
+                let r = RBTree::difference_link(&ar, right);
+                RBTree::join2_link(&l, &r)
+            },
+        }
+    }
+
+    fn is_subset_link(a: &Link<T>, b: &Link<T>) -> bool {
+        if Ptr::ptr_eq(a, b) {
+            return true;
+        }
+        if a.size() > b.size() {
+            return false;
+        }
+        match **a {
+            Node::Leaf => true,
+            Node::Knot{ref left, ref right, ref val, ..} => {
+                let (bl, found, br) = RBTree::split_link(b, val);
+                found && RBTree::is_subset_link(left, &bl) && RBTree::is_subset_link(right, &br)
+            },
+        }
+    }
+
+    // When both trees have equivalent elements, the elements in `self` are kept
+    pub fn union(&self, other: &Self) -> Self {
+        RBTree{root: RBTree::make_black(&RBTree::union_link(&self.root, &other.root))}
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        RBTree{root: RBTree::make_black(&RBTree::intersection_link(&self.root, &other.root))}
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        RBTree{root: RBTree::make_black(&RBTree::difference_link(&self.root, &other.root))}
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        RBTree::is_subset_link(&self.root, &other.root)
+    }
+}
+
+// In-order traversal. The stack holds nodes whose left subtrees were already visited.
+pub struct Iter<'a, T: 'a + Clone + PartialOrd + Debug> {
+    stack: Vec<&'a Node<T>>,
+}
+
+impl<'a, T> Iter<'a, T>
+where T: Clone + PartialOrd + Debug {
+    fn push_lefts(&mut self, mut node: &'a Node<T>) {
+        while let Node::Knot{ref left, ..} = *node {
+            self.stack.push(node);
+            node = left;
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T>
+where T: Clone + PartialOrd + Debug {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.stack.pop() {
+            Some(Node::Knot{val, right, ..}) => {
+                self.push_lefts(right);
+                Some(val)
+            },
+            _ => None,
+        }
+    }
+}
+
+pub struct Range<'a, T: 'a + Clone + PartialOrd + Debug> {
+    iter: Iter<'a, T>,
+    end: Bound<T>,
+}
+
+impl<'a, T> Iterator for Range<'a, T>
+where T: Clone + PartialOrd + Debug {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let x = self.iter.next()?;
+        let below_end = match self.end {
+            Bound::Included(ref end) => x <= end,
+            Bound::Excluded(ref end) => x < end,
+            Bound::Unbounded => true,
+        };
+        if below_end {
+            Some(x)
+        } else {
+            self.iter.stack.clear();
+            None
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a RBTree<T>
+where T: Clone + PartialOrd + Debug {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// exercise 2.6: Finite map on top of red-black tree
+//
+// Entries are ordered only by their keys and can be borrowed as their keys, so the map is just
+// a RBTree of entries.
+#[derive(Clone, Debug)]
+struct Entry<K, V> {
+    key: K,
+    val: V,
+}
+
+impl<K: PartialEq, V> PartialEq for Entry<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: PartialOrd, V> PartialOrd for Entry<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.key.partial_cmp(&other.key)
+    }
+}
+
+impl<K, V> Borrow<K> for Entry<K, V> {
+    fn borrow(&self) -> &K {
+        &self.key
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RBMap<K: Clone + PartialOrd + Debug, V: Clone + Debug> {
+    tree: RBTree<Entry<K, V>>,
+}
+
+impl<K, V> RBMap<K, V>
+where K: Clone + PartialOrd + Debug, V: Clone + Debug {
+    pub fn empty() -> Self {
+        RBMap{tree: RBTree::empty()}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn get(&self, k: &K) -> Option<&V> {
+        self.tree.get(k).map(|e| &e.val)
+    }
+
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.tree.member(k)
+    }
+
+    // Value of existing key is replaced.
+    pub fn insert(&self, key: K, val: V) -> Self {
+        RBMap{tree: self.tree.insert_impl(Entry{key, val}, true)}
+    }
+
+    pub fn remove(&self, k: &K) -> Self {
+        RBMap{tree: self.tree.remove(k)}
+    }
+
+    // Updates the value of the key with `f`. `f` receives the current value if exists. When it returns
+    // None, the key is removed.
+    pub fn update<F>(&self, key: K, f: F) -> Self
+    where F: FnOnce(Option<&V>) -> Option<V> {
+        match f(self.get(&key)) {
+            Some(val) => self.insert(key, val),
+            None => self.remove(&key),
+        }
+    }
+
+    // Iterates entries in order of keys
+    pub fn iter(&self) -> MapIter<'_, K, V> {
+        MapIter{entries: self.tree.iter()}
+    }
+}
+
+pub struct MapIter<'a, K: 'a + Clone + PartialOrd + Debug, V: 'a + Clone + Debug> {
+    entries: Iter<'a, Entry<K, V>>,
+}
+
+impl<'a, K, V> Iterator for MapIter<'a, K, V>
+where K: Clone + PartialOrd + Debug, V: Clone + Debug {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(|e| (&e.key, &e.val))
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a RBMap<K, V>
+where K: Clone + PartialOrd + Debug, V: Clone + Debug {
+    type Item = (&'a K, &'a V);
+    type IntoIter = MapIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> traits::Set<T> for RBTree<T>
+where T: Clone + PartialOrd + Debug {
+    fn empty() -> Self {
+        RBTree::empty()
+    }
+
+    fn insert(&self, x: T) -> Self {
+        self.insert(x)
+    }
+
+    fn member(&self, x: &T) -> bool {
+        self.member(x)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use counted::{Counted, clones};
 
     #[test]
     fn test() {
@@ -173,10 +834,339 @@ mod tests {
         let t = t.insert(10).insert(3).insert(7).insert(1).insert(9);
         assert!(!t.is_empty());
         match *t.root {
-            Node::Leaf => assert!(false),
-            Node::Knot{color: _, left: _, right: _, val} => {
+            Node::Leaf => unreachable!(),
+            Node::Knot{color: _, left: _, right: _, val, ..} => {
                 assert_eq!(val, 7);
             },
         }
     }
+
+    // Returns black height of the tree. Panics when the tree breaks any invariant of red-black tree.
+    // map_or() is used instead of is_none_or(), which needs Rust 1.82.
+    #[allow(clippy::unnecessary_map_or)]
+    fn check_invariants<T>(link: &Link<T>, lo: Option<&T>, hi: Option<&T>) -> usize
+    where T: Clone + PartialOrd + Debug {
+        match **link {
+            Node::Leaf => 1,
+            Node::Knot{ref color, ref left, ref right, ref val, size, bh} => {
+                assert_eq!(size, left.size() + right.size() + 1, "wrong size at {:?}", val);
+                assert!(lo.map_or(true, |lo| lo < val), "{:?} must be greater than {:?}", val, lo);
+                assert!(hi.map_or(true, |hi| val < hi), "{:?} must be less than {:?}", val, hi);
+                if *color == Color::Red {
+                    assert!(!matches!(**left, Node::Knot{color: Color::Red, ..}), "red-red at {:?}", val);
+                    assert!(!matches!(**right, Node::Knot{color: Color::Red, ..}), "red-red at {:?}", val);
+                }
+                let l = check_invariants(left, lo, Some(val));
+                let r = check_invariants(right, Some(val), hi);
+                assert_eq!(l, r, "black heights are different at {:?}", val);
+                let h = if *color == Color::Black { l + 1 } else { l };
+                assert_eq!(bh + 1, h, "wrong black height at {:?}", val);
+                h
+            },
+        }
+    }
+
+    fn check<T: Clone + PartialOrd + Debug>(t: &RBTree<T>) {
+        check_invariants(&t.root, None, None);
+    }
+
+    // xorshift32 so that randomized tests are reproducible without external crates
+    struct Rand(u32);
+
+    impl Rand {
+        fn next(&mut self, n: u32) -> i32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            (self.0 % n) as i32
+        }
+    }
+
+    #[test]
+    fn test_remove() {
+        let t = RBTree::empty().insert(10).insert(3).insert(7).insert(1).insert(9);
+        let t2 = t.remove(&7);
+        check(&t2);
+        assert!(!t2.member(&7));
+        for i in &[1, 3, 9, 10] {
+            assert!(t2.member(i));
+        }
+        // Old version is not changed
+        assert!(t.member(&7));
+
+        // Removing missing element does nothing
+        let t3 = t2.remove(&7);
+        check(&t3);
+        assert!(!t3.member(&7));
+
+        let t4 = [1, 3, 9, 10].iter().fold(t3, |t, i| t.remove(i));
+        assert!(t4.is_empty());
+        assert!(RBTree::<i32>::empty().remove(&1).is_empty());
+    }
+
+    #[test]
+    fn test_random_insert_remove() {
+        let mut rand = Rand(2463534242);
+        let mut t = RBTree::empty();
+        let mut present = vec![false; 256];
+        for _ in 0..5000 {
+            let x = rand.next(256);
+            if rand.next(3) == 0 {
+                t = t.remove(&x);
+                present[x as usize] = false;
+            } else {
+                t = t.insert(x);
+                present[x as usize] = true;
+            }
+            check(&t);
+        }
+        for (i, p) in present.iter().enumerate() {
+            assert_eq!(t.member(&(i as i32)), *p);
+        }
+    }
+
+    #[test]
+    fn test_iter() {
+        let t = RBTree::empty().insert(10).insert(3).insert(7).insert(1).insert(9);
+        assert_eq!(t.iter().cloned().collect::<Vec<_>>(), vec![1, 3, 7, 9, 10]);
+        let mut v = vec![];
+        for x in &t.remove(&7) {
+            v.push(*x);
+        }
+        assert_eq!(v, vec![1, 3, 9, 10]);
+        assert!(RBTree::<i32>::empty().iter().next().is_none());
+    }
+
+    #[test]
+    fn test_map() {
+        let m = RBMap::empty();
+        assert!(m.is_empty());
+        let m = m.insert(3, "three").insert(1, "one").insert(2, "two");
+        assert!(!m.is_empty());
+        assert_eq!(m.get(&1), Some(&"one"));
+        assert_eq!(m.get(&3), Some(&"three"));
+        assert_eq!(m.get(&4), None);
+        assert!(m.contains_key(&2));
+        assert!(!m.contains_key(&4));
+
+        // Insert replaces the value
+        let m2 = m.insert(2, "TWO");
+        assert_eq!(m2.get(&2), Some(&"TWO"));
+        assert_eq!(m.get(&2), Some(&"two"));
+
+        let m3 = m2.remove(&1);
+        assert!(!m3.contains_key(&1));
+        assert!(m2.contains_key(&1));
+        check(&m3.tree);
+    }
+
+    #[test]
+    fn test_map_update() {
+        let m = RBMap::empty().insert("a", 1);
+        let m = m.update("a", |v| v.map(|v| v + 10));
+        assert_eq!(m.get(&"a"), Some(&11));
+        let m = m.update("b", |v| Some(v.map_or(0, |v| v + 1)));
+        assert_eq!(m.get(&"b"), Some(&0));
+        let m = m.update("a", |_| None);
+        assert!(!m.contains_key(&"a"));
+        let m = m.update("c", |_| None);
+        assert!(!m.contains_key(&"c"));
+        assert_eq!(m.iter().collect::<Vec<_>>(), vec![(&"b", &0)]);
+    }
+
+    #[test]
+    fn test_map_iter() {
+        let mut rand = Rand(88172645);
+        let mut m = RBMap::empty();
+        let mut expected = vec![None; 64];
+        for _ in 0..1000 {
+            let k = rand.next(64);
+            let v = rand.next(1000);
+            if rand.next(4) == 0 {
+                m = m.remove(&k);
+                expected[k as usize] = None;
+            } else {
+                m = m.insert(k, v);
+                expected[k as usize] = Some(v);
+            }
+            check(&m.tree);
+        }
+        let expected = expected.iter()
+            .enumerate()
+            .filter_map(|(k, v)| v.map(|v| (k as i32, v)))
+            .collect::<Vec<_>>();
+        let actual = m.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_min_max() {
+        let t = RBTree::<i32>::empty();
+        assert_eq!(t.min(), None);
+        assert_eq!(t.max(), None);
+        let t = t.insert(10).insert(3).insert(7).insert(1).insert(9);
+        assert_eq!(t.min(), Some(&1));
+        assert_eq!(t.max(), Some(&10));
+    }
+
+    #[test]
+    fn test_predecessor_successor() {
+        let t = RBTree::from_sorted(&[1, 3, 5, 7, 9]);
+        assert_eq!(t.predecessor(&1), None);
+        assert_eq!(t.predecessor(&5), Some(&3));
+        assert_eq!(t.predecessor(&6), Some(&5));
+        assert_eq!(t.predecessor(&100), Some(&9));
+        assert_eq!(t.successor(&0), Some(&1));
+        assert_eq!(t.successor(&5), Some(&7));
+        assert_eq!(t.successor(&6), Some(&7));
+        assert_eq!(t.successor(&9), None);
+    }
+
+    #[test]
+    fn test_rank_select() {
+        let t = (0..100).map(|i| i * 2).fold(RBTree::empty(), |t, i| t.insert(i));
+        assert_eq!(t.len(), 100);
+        for i in 0..100 {
+            assert_eq!(t.select(i as usize), Some(&(i * 2)));
+            assert_eq!(t.rank(&(i * 2)), i as usize);
+            assert_eq!(t.rank(&(i * 2 + 1)), i as usize + 1);
+        }
+        assert_eq!(t.select(100), None);
+        assert_eq!(t.remove(&0).select(0), Some(&2));
+        assert_eq!(t.remove(&0).len(), 99);
+    }
+
+    #[test]
+    fn test_range() {
+        let t = RBTree::from_sorted(&(0..20).collect::<Vec<_>>());
+        let collect = |r: Range<i32>| r.cloned().collect::<Vec<_>>();
+        assert_eq!(collect(t.range(3..7)), vec![3, 4, 5, 6]);
+        assert_eq!(collect(t.range(3..=7)), vec![3, 4, 5, 6, 7]);
+        assert_eq!(collect(t.range(..3)), vec![0, 1, 2]);
+        assert_eq!(collect(t.range(17..)), vec![17, 18, 19]);
+        assert_eq!(collect(t.range(..)).len(), 20);
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = t.range(7..3);
+        assert_eq!(collect(reversed), vec![]);
+        assert_eq!(collect(t.range((Bound::Excluded(3), Bound::Excluded(5)))), vec![4]);
+        assert_eq!(collect(t.range(30..40)), vec![]);
+    }
+
+    #[test]
+    fn test_split() {
+        let mut rand = Rand(123456789);
+        for n in 0..50 {
+            let t = (0..n).fold(RBTree::empty(), |t, i| t.insert(i * 2));
+            let k = rand.next(2 * n as u32 + 2);
+            let (l, found, r) = t.split(&k);
+            check(&l);
+            check(&r);
+            assert_eq!(found, t.member(&k));
+            assert_eq!(l.iter().cloned().collect::<Vec<_>>(), t.range(..k).cloned().collect::<Vec<_>>());
+            assert_eq!(r.iter().cloned().collect::<Vec<_>>(), t.range(k + 1..).cloned().collect::<Vec<_>>());
+            assert_eq!(l.len() + r.len() + found as usize, t.len());
+        }
+    }
+
+    #[test]
+    fn test_join() {
+        let l = RBTree::from_sorted(&(0..10).collect::<Vec<_>>());
+        let r = RBTree::from_sorted(&(11..100).collect::<Vec<_>>());
+        let t = RBTree::join(&l, 10, &r);
+        check(&t);
+        assert_eq!(t.iter().cloned().collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+        let t = RBTree::join(&RBTree::empty(), 0, &RBTree::empty());
+        check(&t);
+        assert_eq!(t.len(), 1);
+    }
+
+    fn random_tree(rand: &mut Rand, n: usize, max: u32) -> RBTree<i32> {
+        (0..n).fold(RBTree::empty(), |t, _| t.insert(rand.next(max)))
+    }
+
+    #[test]
+    fn test_set_operations() {
+        use std::collections::BTreeSet;
+
+        let mut rand = Rand(2463534242);
+        for i in 0..100 {
+            let a = random_tree(&mut rand, i % 23 * 3, 64);
+            let b = random_tree(&mut rand, i % 17 * 4, 64);
+            let sa = a.iter().cloned().collect::<BTreeSet<_>>();
+            let sb = b.iter().cloned().collect::<BTreeSet<_>>();
+
+            let u = a.union(&b);
+            check(&u);
+            assert_eq!(u.iter().cloned().collect::<Vec<_>>(), sa.union(&sb).cloned().collect::<Vec<_>>());
+            assert_eq!(u.len(), sa.union(&sb).count());
+
+            let n = a.intersection(&b);
+            check(&n);
+            assert_eq!(n.iter().cloned().collect::<Vec<_>>(), sa.intersection(&sb).cloned().collect::<Vec<_>>());
+
+            let d = a.difference(&b);
+            check(&d);
+            assert_eq!(d.iter().cloned().collect::<Vec<_>>(), sa.difference(&sb).cloned().collect::<Vec<_>>());
+
+            assert_eq!(a.is_subset(&b), sa.is_subset(&sb));
+            assert!(a.is_subset(&u));
+            assert!(n.is_subset(&a));
+            assert!(d.is_subset(&a));
+            assert!(!d.is_empty() || a.is_subset(&b));
+        }
+    }
+
+    #[test]
+    fn test_set_operations_sharing() {
+        let a = RBTree::from_sorted(&(0..1000).collect::<Vec<_>>());
+        let b = a.insert(1000).remove(&500);
+        let u = a.union(&b);
+        check(&u);
+        assert_eq!(u.len(), 1001);
+        let n = a.intersection(&b);
+        check(&n);
+        assert_eq!(n.len(), 999);
+        assert!(!n.member(&500));
+        assert_eq!(a.difference(&b).iter().cloned().collect::<Vec<_>>(), vec![500]);
+        assert_eq!(b.difference(&a).iter().cloned().collect::<Vec<_>>(), vec![1000]);
+        assert!(a.difference(&a).is_empty());
+        assert!(a.is_subset(&a));
+        assert!(n.is_subset(&b));
+        assert!(!a.is_subset(&b));
+    }
+
+    #[test]
+    fn test_set_operations_sharing_cost() {
+        // Only elements around the differing paths are copied for two versions of a large tree
+        let n = 10000;
+        let a = RBTree::from_sorted(&(0..n).map(Counted).collect::<Vec<_>>());
+        for b in &[a.insert(Counted(n)), a.remove(&Counted(n / 3)), a.insert(Counted(-1)).remove(&Counted(n / 2))] {
+            let before = clones();
+            let u = a.union(b);
+            let i = a.intersection(b);
+            let d = a.difference(b);
+            let copies = clones() - before;
+            assert!(copies < 1000, "copies: {}", copies);
+            check(&u);
+            check(&i);
+            check(&d);
+            assert!(i.is_subset(&u));
+        }
+    }
+
+    #[test]
+    fn test_from_sorted() {
+        for n in 0..100 {
+            let v = (0..n).collect::<Vec<_>>();
+            let t = RBTree::from_sorted(&v);
+            check(&t);
+            for i in &v {
+                assert!(t.member(i));
+            }
+            assert!(!t.member(&n));
+            // Tree from from_sorted() can be updated as well
+            let t = t.insert(n).remove(&0);
+            check(&t);
+        }
+    }
 }
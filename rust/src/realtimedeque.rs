@@ -0,0 +1,298 @@
+// Section 8.4.3
+//
+// Real-time deque.
+// Worst-case O(1) operations on both ends
+
+use std::fmt::Debug;
+use stream::{Stream, StreamCell, Cell};
+use ptr::Shareable;
+use traits;
+
+// Note:
+// Invariants: |f| <= c|r| + 1 and |r| <= c|f| + 1
+// Rebalancing is the same as banker's deque, but `reverse` and `drop` are replaced with `rotate_rev`
+// and `rotate_drop` which process c elements of the longer side per step of the shorter side.
+// sf and sr are schedules of f and r. Each operation forces one or two cells of them so that the
+// rotations are completed before the next rebalance.
+#[derive(Clone, Debug)]
+pub struct RealTimeDeque<'a, T: 'a + Clone + Debug + Shareable> {
+    c: usize,
+    lenf: usize,
+    f: Stream<'a, T>,
+    sf: Stream<'a, T>,
+    lenr: usize,
+    r: Stream<'a, T>,
+    sr: Stream<'a, T>,
+}
+
+impl<'a, T> RealTimeDeque<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    pub fn empty() -> Self {
+        RealTimeDeque::with_balance(3)
+    }
+
+    // The schedules force the rotations in time only when c is 2 or 3. Returns None for other c.
+    pub fn try_with_balance(c: usize) -> Option<Self> {
+        if c != 2 && c != 3 {
+            return None;
+        }
+        let s = Stream::empty();
+        Some(RealTimeDeque{c, lenf: 0, f: s.clone(), sf: s.clone(), lenr: 0, r: s.clone(), sr: s})
+    }
+
+    pub fn with_balance(c: usize) -> Self {
+        RealTimeDeque::try_with_balance(c).expect("balance constant must be 2 or 3!")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lenf + self.lenr == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.lenf + self.lenr
+    }
+
+    fn exec1(s: &Stream<'a, T>) -> Stream<'a, T> {
+        s.try_tail().unwrap_or_else(|| s.clone())
+    }
+
+    fn exec2(s: &Stream<'a, T>) -> Stream<'a, T> {
+        RealTimeDeque::exec1(&RealTimeDeque::exec1(s))
+    }
+
+    // Forces the first cell. `take`, `drop` and `reverse` of streams are suspended, so nesting them
+    // step by step would build a chain of suspensions and forcing the last one would take O(n). The
+    // streams are forced at each step instead. It takes O(c) since their sources are already forced.
+    fn forced(s: Stream<'a, T>) -> Stream<'a, T> {
+        s.0.force();
+        s
+    }
+
+    // Calculates `f ++ reverse r ++ a` moving c elements of r for each element of f
+    fn rotate_rev(c: usize, f: &Cell<'a, T>, r: Stream<'a, T>, a: Stream<'a, T>) -> StreamCell<'a, T> {
+        match *f.eval() {
+            StreamCell::Nil => r.reverse().concat(&a).0.eval().clone(),
+            StreamCell::Cons(ref x, ref f) => {
+                let f = f.clone();
+                let a = RealTimeDeque::forced(r.take(c).reverse()).concat(&a);
+                let r = RealTimeDeque::forced(r.drop(c));
+                StreamCell::Cons(x.clone(), lazily!{
+                    RealTimeDeque::rotate_rev(c, &f, r, a)
+                })
+            },
+        }
+    }
+
+    // Calculates `f ++ reverse (drop j r)` dropping c elements of r for each element of f
+    fn rotate_drop(c: usize, f: Stream<'a, T>, j: usize, r: Stream<'a, T>) -> Stream<'a, T> {
+        Stream(lazily!{
+            if j < c {
+                RealTimeDeque::rotate_rev(c, &f.0, RealTimeDeque::forced(r.drop(j)), Stream::empty())
+            } else {
+                let (x, f) = f.uncons().expect("shorter side must have j / c elements at least");
+                let r = RealTimeDeque::forced(r.drop(c));
+                StreamCell::Cons(x.clone(), RealTimeDeque::rotate_drop(c, f, j - c, r).0)
+            }
+        })
+    }
+
+    fn check(&self, lenf: usize, f: Stream<'a, T>, sf: Stream<'a, T>,
+             lenr: usize, r: Stream<'a, T>, sr: Stream<'a, T>) -> Self {
+        let c = self.c;
+        let len = lenf + lenr;
+        if lenf > c * lenr + 1 {
+            let i = len / 2;
+            let r = RealTimeDeque::rotate_drop(c, r, i, f.clone());
+            let f = f.take(i);
+            RealTimeDeque{c, lenf: i, f: f.clone(), sf: f, lenr: len - i, r: r.clone(), sr: r}
+        } else if lenr > c * lenf + 1 {
+            let j = len / 2;
+            let f = RealTimeDeque::rotate_drop(c, f, j, r.clone());
+            let r = r.take(j);
+            RealTimeDeque{c, lenf: len - j, f: f.clone(), sf: f, lenr: j, r: r.clone(), sr: r}
+        } else {
+            RealTimeDeque{c, lenf, f, sf, lenr, r, sr}
+        }
+    }
+
+    pub fn enq_front(&self, x: T) -> Self {
+        let sf = RealTimeDeque::exec1(&self.sf);
+        let sr = RealTimeDeque::exec1(&self.sr);
+        self.check(self.lenf + 1, self.f.cons(x), sf, self.lenr, self.r.clone(), sr)
+    }
+
+    pub fn enq_back(&self, x: T) -> Self {
+        let sf = RealTimeDeque::exec1(&self.sf);
+        let sr = RealTimeDeque::exec1(&self.sr);
+        self.check(self.lenf, self.f.clone(), sf, self.lenr + 1, self.r.cons(x), sr)
+    }
+
+    pub fn try_deq_front(&self) -> Option<Self> {
+        match self.f.try_tail() {
+            Some(f) => {
+                let sf = RealTimeDeque::exec2(&self.sf);
+                let sr = RealTimeDeque::exec2(&self.sr);
+                Some(self.check(self.lenf - 1, f, sf, self.lenr, self.r.clone(), sr))
+            },
+            // Only one element is in r due to invariant
+            None if self.lenr > 0 => Some(RealTimeDeque::with_balance(self.c)),
+            None => None,
+        }
+    }
+
+    pub fn try_deq_back(&self) -> Option<Self> {
+        match self.r.try_tail() {
+            Some(r) => {
+                let sf = RealTimeDeque::exec2(&self.sf);
+                let sr = RealTimeDeque::exec2(&self.sr);
+                Some(self.check(self.lenf, self.f.clone(), sf, self.lenr - 1, r, sr))
+            },
+            // Only one element is in f due to invariant
+            None if self.lenf > 0 => Some(RealTimeDeque::with_balance(self.c)),
+            None => None,
+        }
+    }
+
+    pub fn deq_front(&self) -> Self {
+        self.try_deq_front().expect("Deque is empty!")
+    }
+
+    pub fn deq_back(&self) -> Self {
+        self.try_deq_back().expect("Deque is empty!")
+    }
+
+    pub fn try_front(&self) -> Option<&T> {
+        self.f.try_head().or_else(|| self.r.try_head())
+    }
+
+    pub fn try_back(&self) -> Option<&T> {
+        self.r.try_head().or_else(|| self.f.try_head())
+    }
+
+    pub fn front(&self) -> &T {
+        self.try_front().expect("Deque is empty!")
+    }
+
+    pub fn back(&self) -> &T {
+        self.try_back().expect("Deque is empty!")
+    }
+
+    pub fn pop_front(&self) -> Option<(&T, Self)> {
+        self.try_front().and_then(|x| self.try_deq_front().map(|d| (x, d)))
+    }
+
+    pub fn pop_back(&self) -> Option<(&T, Self)> {
+        self.try_back().and_then(|x| self.try_deq_back().map(|d| (x, d)))
+    }
+}
+
+impl<'a, T> traits::Deque<T> for RealTimeDeque<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn empty() -> Self {
+        RealTimeDeque::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn enq_front(&self, x: T) -> Self {
+        self.enq_front(x)
+    }
+
+    fn enq_back(&self, x: T) -> Self {
+        self.enq_back(x)
+    }
+
+    fn try_front(&self) -> Option<&T> {
+        self.try_front()
+    }
+
+    fn try_back(&self) -> Option<&T> {
+        self.try_back()
+    }
+
+    fn try_deq_front(&self) -> Option<Self> {
+        self.try_deq_front()
+    }
+
+    fn try_deq_back(&self) -> Option<Self> {
+        self.try_deq_back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use counted::{Counted, clones};
+
+    #[test]
+    fn test_enq_deq() {
+        let d = RealTimeDeque::<i32>::empty();
+        assert!(d.is_empty());
+        assert!(d.try_front().is_none());
+        assert!(d.try_deq_back().is_none());
+        let d = d.enq_back(2).enq_front(1).enq_back(3);
+        assert_eq!(d.len(), 3);
+        assert_eq!(d.front(), &1);
+        assert_eq!(d.back(), &3);
+        let d = d.deq_front().deq_front();
+        assert_eq!(d.front(), &3);
+        assert_eq!(d.back(), &3);
+        assert!(d.deq_back().is_empty());
+    }
+
+    #[test]
+    fn test_one_side() {
+        let mut d = RealTimeDeque::empty();
+        for i in 0..100 {
+            d = d.enq_back(i);
+        }
+        for i in 0..100 {
+            assert_eq!(d.front(), &i);
+            d = d.deq_front();
+        }
+        assert!(d.is_empty());
+        for i in 0..100 {
+            d = d.enq_front(i);
+        }
+        for i in 0..100 {
+            assert_eq!(d.front(), &(99 - i));
+            d = d.deq_front();
+        }
+        assert!(d.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_balance() {
+        assert!(RealTimeDeque::<i32>::try_with_balance(4).is_none());
+        assert!(RealTimeDeque::<i32>::try_with_balance(1).is_none());
+        assert!(RealTimeDeque::<i32>::try_with_balance(2).is_some());
+    }
+
+    #[test]
+    fn test_worst_case() {
+        // The work of each operation does not depend on the number of elements
+        type D = RealTimeDeque<'static, Counted>;
+        let ops: &[fn(&D, i32) -> D] = &[
+            |d, i| d.enq_front(Counted(i)),
+            |d, i| d.enq_back(Counted(i)),
+            |d, _| d.try_deq_front().unwrap_or_else(|| d.clone()),
+            |d, _| d.try_deq_back().unwrap_or_else(|| d.clone()),
+        ];
+        // Each pattern is a sequence of indices of `ops`
+        let patterns: &[&[usize]] = &[&[0, 0, 3], &[1, 1, 2], &[0, 1, 2, 0, 3], &[0, 0, 0, 0, 2, 2, 2]];
+        for pattern in patterns {
+            let mut d = RealTimeDeque::empty();
+            let mut max = 0;
+            for i in 0..3000 {
+                let before = clones();
+                d = ops[pattern[i % pattern.len()]](&d, i as i32);
+                d.try_front();
+                d.try_back();
+                max = max.max(clones() - before);
+            }
+            assert!(max <= 20, "max copies per operation: {}", max);
+        }
+    }
+}
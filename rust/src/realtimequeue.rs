@@ -0,0 +1,179 @@
+// Section 7.2
+//
+// Real-time queue.
+// Worst-case O(1) operations (enqueue/dequeue)
+
+use std::fmt::Debug;
+use lazy::Delayed;
+use stream::{Stream, StreamCell, Cell};
+use list::{List, Node};
+use ptr::Shareable;
+use traits;
+
+// Note:
+// Invariants: |s| = |f| - |r|
+// The schedule s is a suffix of f whose cells are not forced yet. Each operation forces one cell of
+// the schedule, so the rotation `f ++ reverse r` is evaluated incrementally and has been completed
+// before the front stream reaches the reversed part. As a result, no operation forces more than
+// constant work even if old versions are used persistently.
+#[derive(Clone, Debug)]
+pub struct RealTimeQueue<'a, T: 'a + Clone + Debug + Shareable> {
+    f: Stream<'a, T>,
+    r: List<T>,
+    s: Stream<'a, T>,
+}
+
+impl<'a, T> RealTimeQueue<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    pub fn empty() -> Self {
+        RealTimeQueue{f: Stream::empty(), r: List::empty(), s: Stream::empty()}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.f.is_empty()
+    }
+
+    // Calculates `f ++ reverse r ++ a` one cell at a time where |r| = |f| + 1
+    fn rotate(f: &Cell<'a, T>, r: &List<T>, a: Cell<'a, T>) -> StreamCell<'a, T> {
+        match (f.eval(), r.root()) {
+            (StreamCell::Nil, Node::Cons(y, _)) => StreamCell::Cons(y.clone(), a),
+            (StreamCell::Cons(x, xs), Node::Cons(y, ys)) => {
+                let xs = xs.clone();
+                let ys = ys.clone();
+                let a = Delayed::constant(StreamCell::Cons(y.clone(), a));
+                StreamCell::Cons(x.clone(), lazily!{
+                    RealTimeQueue::rotate(&xs, &ys, a)
+                })
+            },
+            _ => unreachable!("rear list must be longer than front stream by one"),
+        }
+    }
+
+    fn exec(f: Stream<'a, T>, r: List<T>, s: Stream<'a, T>) -> Self {
+        match s.try_tail() {
+            Some(s) => RealTimeQueue{f, r, s},
+            None => {
+                let f = Stream(lazily!{
+                    RealTimeQueue::rotate(&f.0, &r, Delayed::constant(StreamCell::Nil))
+                });
+                RealTimeQueue{f: f.clone(), r: List::empty(), s: f}
+            },
+        }
+    }
+
+    pub fn enq(&self, x: T) -> Self {
+        RealTimeQueue::exec(self.f.clone(), self.r.cons(x), self.s.clone())
+    }
+
+    pub fn pop(&self) -> Option<(&T, Self)> {
+        self.f.uncons().map(|(x, f)| (x, RealTimeQueue::exec(f, self.r.clone(), self.s.clone())))
+    }
+
+    pub fn try_top(&self) -> Option<&T> {
+        self.f.try_head()
+    }
+
+    pub fn try_deq(&self) -> Option<Self> {
+        self.pop().map(|(_, q)| q)
+    }
+
+    pub fn top(&self) -> &T {
+        self.try_top().expect("Queue is empty")
+    }
+
+    pub fn deq(&self) -> Self {
+        self.try_deq().expect("Queue is empty")
+    }
+}
+
+impl<'a, T> traits::Queue<T> for RealTimeQueue<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn empty() -> Self {
+        RealTimeQueue::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn enq(&self, x: T) -> Self {
+        self.enq(x)
+    }
+
+    fn try_top(&self) -> Option<&T> {
+        self.try_top()
+    }
+
+    fn try_deq(&self) -> Option<Self> {
+        self.try_deq()
+    }
+
+    fn pop(&self) -> Option<(&T, Self)> {
+        self.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use counted::{Counted, clones};
+
+    #[test]
+    fn test_pop() {
+        let q = RealTimeQueue::<i32>::empty();
+        assert!(q.is_empty());
+        assert!(q.pop().is_none());
+        let q = q.enq(1).enq(2).enq(3);
+        let (x, q) = q.pop().unwrap();
+        assert_eq!(x, &1);
+        let mut q = q.enq(4);
+        let mut v = vec![];
+        while let Some((x, rest)) = q.pop() {
+            v.push(*x);
+            q = rest;
+        }
+        assert_eq!(v, vec![2, 3, 4]);
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn test_fifo() {
+        let mut q = RealTimeQueue::empty();
+        let mut expected = 0;
+        for i in 0..1000 {
+            q = q.enq(i);
+            if i % 3 == 0 {
+                assert_eq!(q.top(), &expected);
+                q = q.deq();
+                expected += 1;
+            }
+        }
+        while !q.is_empty() {
+            assert_eq!(q.top(), &expected);
+            q = q.deq();
+            expected += 1;
+        }
+        assert_eq!(expected, 1000);
+    }
+
+    #[test]
+    fn test_worst_case() {
+        // Every operation on every version copies at most two elements while forcing the schedule
+        let mut versions = vec![RealTimeQueue::empty()];
+        for i in 0..1000 {
+            let q = versions.last().unwrap().clone();
+            let before = clones();
+            let q = if i % 4 == 3 { q.deq() } else { q.enq(Counted(i)) };
+            assert!(clones() - before <= 2);
+            versions.push(q);
+        }
+        for q in &versions {
+            for _ in 0..3 {
+                let before = clones();
+                let _ = q.enq(Counted(-1));
+                let _ = q.try_deq();
+                assert!(clones() - before <= 4);
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+// Section 9.3.2
+//
+// Skew binomial heap.
+// Worst-case O(1) insert, and O(log n) merge, find_min and delete_min
+
+use std::fmt::Debug;
+use std::cmp::Ord;
+use list::{List, Node};
+use binheap::{self, TreeNode};
+use traits;
+
+// Same as a tree of binomial heap but each node has a list of extra elements added by skew links. A
+// tree of rank r has up to r extra elements. Since a skew link does not add a child, children of a
+// tree of rank r still have ranks r - 1, ..., 0 in this order and ranks are not stored in nodes.
+type Tree<T> = binheap::Tree<T, List<T>>;
+type Trees<T> = binheap::Trees<T, List<T>>;
+
+// Links two trees of the same rank with a new element. The larger one of the element and the root of
+// the linked tree is put in the extra elements.
+fn skew_link<T: Clone + Ord + Debug>(x: T, t1: &Tree<T>, t2: &Tree<T>) -> Tree<T> {
+    let Tree{rank, root: TreeNode{val, extra, children}} = t1.link(t2);
+    let (val, extra) = if x <= val { (x, extra.cons(val)) } else { (val, extra.cons(x)) };
+    Tree{rank, root: TreeNode{val, extra, children}}
+}
+
+// Note:
+// Trees are sorted by increasing rank like binomial heap, except that the first two trees can have
+// the same rank. On insertion, the two trees are linked with the new element by a skew link instead
+// of cascading links like binary carry. Other operations normalize the list of trees at first so
+// that the ranks are distinct. The number of elements is cached since trees don't have fixed sizes.
+#[derive(Clone, Debug)]
+pub struct SkewBinHeap<T: Clone + Ord + Debug> {
+    trees: Trees<T>,
+    size: usize,
+}
+
+impl<T> SkewBinHeap<T>
+where T: Clone + Ord + Debug {
+    pub fn empty() -> Self {
+        SkewBinHeap{trees: List::empty(), size: 0}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trees.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    // Iterates elements in ascending order
+    pub fn iter(&self) -> traits::HeapIter<T, Self> {
+        traits::HeapIter::new(self.clone())
+    }
+
+    // Makes ranks of trees distinct
+    fn normalize(ts: &Trees<T>) -> Trees<T> {
+        match *ts.root() {
+            Node::Nil => ts.clone(),
+            Node::Cons(ref t, ref ts) => Tree::insert_tree(t.clone(), ts),
+        }
+    }
+
+    fn insert_elem(x: T, ts: &Trees<T>) -> Trees<T> {
+        if let Node::Cons(ref t1, ref rest) = *ts.root() {
+            if let Node::Cons(ref t2, ref rest) = *rest.root() {
+                if t1.rank == t2.rank {
+                    return rest.cons(skew_link(x, t1, t2));
+                }
+            }
+        }
+        ts.cons(Tree::leaf(x, List::empty()))
+    }
+
+    pub fn insert(&self, x: T) -> Self {
+        SkewBinHeap{trees: SkewBinHeap::insert_elem(x, &self.trees), size: self.size + 1}
+    }
+
+    pub fn merge(&self, other: &Self) -> Self {
+        let trees = Tree::merge_trees(&SkewBinHeap::normalize(&self.trees), &SkewBinHeap::normalize(&other.trees));
+        SkewBinHeap{trees, size: self.size + other.size}
+    }
+
+    pub fn try_find_min(&self) -> Option<&T> {
+        Tree::find_min_root(&self.trees).map(|t| &t.root.val)
+    }
+
+    pub fn find_min(&self) -> &T {
+        self.try_find_min().expect("heap is empty!")
+    }
+
+    // The extra elements of the minimum root are inserted again after its children are merged
+    pub fn pop_min(&self) -> Option<(&T, Self)> {
+        Tree::remove_min_root(&self.trees).map(|(t, ts1)| {
+            let Tree{ref rank, root: TreeNode{ref val, ref extra, ref children}} = *t;
+            let ts2 = Tree::nodes_to_trees(rank - 1, children);
+            let ts = Tree::merge_trees(&SkewBinHeap::normalize(&ts1), &ts2);
+            let trees = extra.iter().fold(ts, |ts, x| SkewBinHeap::insert_elem(x.clone(), &ts));
+            (val, SkewBinHeap{trees, size: self.size - 1})
+        })
+    }
+
+    pub fn try_delete_min(&self) -> Option<Self> {
+        self.pop_min().map(|(_, h)| h)
+    }
+
+    pub fn delete_min(&self) -> Self {
+        self.try_delete_min().expect("heap is empty!")
+    }
+}
+
+impl<T> traits::Heap<T> for SkewBinHeap<T>
+where T: Clone + Ord + Debug {
+    fn empty() -> Self {
+        SkewBinHeap::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn insert(&self, x: T) -> Self {
+        self.insert(x)
+    }
+
+    fn merge(&self, other: &Self) -> Self {
+        self.merge(other)
+    }
+
+    fn try_find_min(&self) -> Option<&T> {
+        self.try_find_min()
+    }
+
+    fn try_delete_min(&self) -> Option<Self> {
+        self.try_delete_min()
+    }
+
+    fn pop_min(&self) -> Option<(&T, Self)> {
+        self.pop_min()
+    }
+}
+
+impl<T> traits::HeapFamily for SkewBinHeap<T>
+where T: Clone + Ord + Debug {
+    type Heap<E: Clone + Ord + Debug> = SkewBinHeap<E>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use counted::{Counted, clones};
+
+    #[test]
+    fn test_insert() {
+        let mut h = SkewBinHeap::empty().insert(3).insert(1).insert(7).insert(10);
+        assert_eq!(h.len(), 4);
+        for i in &[1, 3, 7, 10] {
+            assert_eq!(h.find_min(), i);
+            h = h.delete_min();
+        }
+        assert!(h.is_empty());
+        assert_eq!(h.len(), 0);
+    }
+
+    #[test]
+    fn test_merge() {
+        let h1 = SkewBinHeap::empty().insert(3).insert(1).insert(7).insert(10).insert(5);
+        let h2 = SkewBinHeap::empty().insert(2).insert(4).insert(11).insert(0);
+        let h = h1.merge(&h2);
+        assert_eq!(h.len(), 9);
+        assert_eq!(h.iter().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5, 7, 10, 11]);
+        assert_eq!(h2.merge(&h1).iter().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5, 7, 10, 11]);
+    }
+
+    #[test]
+    fn test_worst_case_insert() {
+        // Insertion does not cascade links even if the heap has 2^k - 1 elements
+        let mut h = SkewBinHeap::empty();
+        let mut max = 0;
+        for i in 0..4096 {
+            let before = clones();
+            h = h.insert(Counted(i));
+            max = max.max(clones() - before);
+        }
+        assert!(max <= 2, "max copies per insertion: {}", max);
+        assert_eq!(h.find_min(), &Counted(0));
+    }
+}
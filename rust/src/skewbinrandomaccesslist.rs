@@ -0,0 +1,345 @@
+// Section 9.3.1
+//
+// Skew binary random-access list.
+// Worst-case O(1) cons, head and tail, and O(log n) lookup and update
+// O(log n) drop (O(log k) to skip whole trees)
+
+use std::fmt::Debug;
+use std::iter::FromIterator;
+use list::{self, List, Node};
+use ptr::Ptr;
+use traits;
+
+// Complete binary tree whose elements are in preorder
+#[derive(Clone, Debug)]
+enum Tree<T: Clone + Debug> {
+    Leaf(T),
+    Node(T, Link<T>, Link<T>),
+}
+
+type Link<T> = Ptr<Tree<T>>;
+
+impl<T> Tree<T>
+where T: Clone + Debug {
+    fn root(&self) -> &T {
+        match *self {
+            Tree::Leaf(ref x) | Tree::Node(ref x, _, _) => x,
+        }
+    }
+
+    // w is the number of elements in the tree and i < w
+    fn lookup(&self, mut w: usize, mut i: usize) -> &T {
+        let mut t = self;
+        loop {
+            match *t {
+                _ if i == 0 => return t.root(),
+                Tree::Leaf(_) => unreachable!("index must be smaller than weight"),
+                Tree::Node(_, ref t1, ref t2) => {
+                    w /= 2;
+                    if i <= w {
+                        i -= 1;
+                        t = t1;
+                    } else {
+                        i -= 1 + w;
+                        t = t2;
+                    }
+                },
+            }
+        }
+    }
+
+    // Only nodes on the path to the i-th element are copied
+    fn update(&self, w: usize, i: usize, x: T) -> Self {
+        match *self {
+            Tree::Leaf(_) => Tree::Leaf(x),
+            Tree::Node(_, ref t1, ref t2) if i == 0 => Tree::Node(x, t1.clone(), t2.clone()),
+            Tree::Node(ref y, ref t1, ref t2) => {
+                let w = w / 2;
+                if i <= w {
+                    Tree::Node(y.clone(), Ptr::new(t1.update(w, i - 1, x)), t2.clone())
+                } else {
+                    Tree::Node(y.clone(), t1.clone(), Ptr::new(t2.update(w, i - 1 - w, x)))
+                }
+            },
+        }
+    }
+}
+
+type Trees<T> = List<(usize, Link<T>)>;
+
+// Note:
+// The spine is a list of complete binary trees with their weights (2^k - 1 for some k) in increasing
+// order. Only the first two trees can have the same weight. `cons` links the first two trees with the
+// new element as the root when they have the same weight, and `tail` splits the first tree into its
+// children, so both touch only the head of the spine.
+#[derive(Clone, Debug)]
+pub struct SkewBinRandomAccessList<T: Clone + Debug> {
+    trees: Trees<T>,
+}
+
+impl<T> SkewBinRandomAccessList<T>
+where T: Clone + Debug {
+    pub fn empty() -> Self {
+        SkewBinRandomAccessList{trees: List::empty()}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trees.is_empty()
+    }
+
+    // O(log n): The number of trees is O(log n).
+    pub fn len(&self) -> usize {
+        self.trees.iter().map(|&(w, _)| w).sum()
+    }
+
+    pub fn cons(&self, x: T) -> Self {
+        if let Node::Cons((w1, ref t1), ref ts) = *self.trees.root() {
+            if let Node::Cons((w2, ref t2), ref ts) = *ts.root() {
+                if w1 == w2 {
+                    let t = Tree::Node(x, t1.clone(), t2.clone());
+                    return SkewBinRandomAccessList{trees: ts.cons((1 + w1 + w2, Ptr::new(t)))};
+                }
+            }
+        }
+        SkewBinRandomAccessList{trees: self.trees.cons((1, Ptr::new(Tree::Leaf(x))))}
+    }
+
+    pub fn try_head(&self) -> Option<&T> {
+        self.trees.try_head().map(|(_, t)| t.root())
+    }
+
+    pub fn try_tail(&self) -> Option<Self> {
+        self.trees.uncons().map(|(&(w, ref t), ts)| {
+            let trees = match **t {
+                Tree::Leaf(_) => ts.clone(),
+                Tree::Node(_, ref t1, ref t2) => ts.cons((w / 2, t2.clone())).cons((w / 2, t1.clone())),
+            };
+            SkewBinRandomAccessList{trees}
+        })
+    }
+
+    pub fn uncons(&self) -> Option<(&T, Self)> {
+        self.try_head().and_then(|x| self.try_tail().map(|xs| (x, xs)))
+    }
+
+    pub fn head(&self) -> &T {
+        self.try_head().expect("List is empty!")
+    }
+
+    pub fn tail(&self) -> Self {
+        self.try_tail().expect("List is empty!")
+    }
+
+    pub fn try_lookup(&self, mut i: usize) -> Option<&T> {
+        for &(w, ref t) in self.trees.iter() {
+            if i < w {
+                return Some(t.lookup(w, i));
+            }
+            i -= w;
+        }
+        None
+    }
+
+    fn update_trees(i: usize, x: T, ts: &Trees<T>) -> Option<Trees<T>> {
+        match *ts.root() {
+            Node::Nil => None,
+            Node::Cons((w, ref t), ref ts) => {
+                if i < w {
+                    Some(ts.cons((w, Ptr::new(t.update(w, i, x)))))
+                } else {
+                    SkewBinRandomAccessList::update_trees(i - w, x, ts).map(|ts| ts.cons((w, t.clone())))
+                }
+            },
+        }
+    }
+
+    // Trees other than the one containing the i-th element are shared
+    pub fn try_update(&self, i: usize, x: T) -> Option<Self> {
+        SkewBinRandomAccessList::update_trees(i, x, &self.trees).map(|trees| SkewBinRandomAccessList{trees})
+    }
+
+    pub fn lookup(&self, i: usize) -> &T {
+        self.try_lookup(i).expect("Index out of bounds!")
+    }
+
+    pub fn update(&self, i: usize, x: T) -> Self {
+        self.try_update(i, x).expect("Index out of bounds!")
+    }
+
+    // Exercise 9.6: Removes the first k elements (or all elements when k >= len).
+    // Whole trees are skipped at first. Since skipped weights sum up to at most k, it takes O(log k).
+    // Then the tree containing the k-th element is split along the path to it. Going to left children
+    // decrements k and going to right children halves it, so the path is O(min(k, log n)).
+    pub fn drop(&self, mut k: usize) -> Self {
+        let mut ts = &self.trees;
+        while let Some((&(w, ref t), rest)) = ts.uncons() {
+            if k < w {
+                return SkewBinRandomAccessList{trees: SkewBinRandomAccessList::drop_tree(k, w, t, rest)};
+            }
+            k -= w;
+            ts = rest;
+        }
+        SkewBinRandomAccessList::empty()
+    }
+
+    fn drop_tree(mut k: usize, mut w: usize, t: &Link<T>, rest: &Trees<T>) -> Trees<T> {
+        // Right children of the nodes where the path goes left remain in the result. The deeper one
+        // precedes the shallower one, and their weights are decreasing along the path, so the result
+        // is still in increasing order of weights.
+        let mut t = t;
+        let mut trees = rest.clone();
+        while k > 0 {
+            match **t {
+                Tree::Leaf(_) => unreachable!("index must be smaller than weight"),
+                Tree::Node(_, ref t1, ref t2) => {
+                    w /= 2;
+                    if k <= w {
+                        trees = trees.cons((w, t2.clone()));
+                        k -= 1;
+                        t = t1;
+                    } else {
+                        k -= 1 + w;
+                        t = t2;
+                    }
+                },
+            }
+        }
+        trees.cons((w, t.clone()))
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter{trees: self.trees.iter(), stack: vec![]}
+    }
+}
+
+pub struct Iter<'a, T: 'a + Clone + Debug> {
+    trees: list::Iter<'a, (usize, Link<T>)>,
+    // Subtrees which are not visited yet
+    stack: Vec<&'a Tree<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T>
+where T: Clone + Debug {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let t = match self.stack.pop() {
+            Some(t) => t,
+            None => match self.trees.next() {
+                Some((_, t)) => t,
+                None => return None,
+            },
+        };
+        if let Tree::Node(_, ref t1, ref t2) = *t {
+            self.stack.push(t2);
+            self.stack.push(t1);
+        }
+        Some(t.root())
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SkewBinRandomAccessList<T>
+where T: Clone + Debug {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> FromIterator<T> for SkewBinRandomAccessList<T>
+where T: Clone + Debug {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let xs = iter.into_iter().collect::<Vec<_>>();
+        xs.into_iter().rev().fold(SkewBinRandomAccessList::empty(), |l, x| l.cons(x))
+    }
+}
+
+impl<T> traits::Stack<T> for SkewBinRandomAccessList<T>
+where T: Clone + Debug {
+    fn empty() -> Self {
+        SkewBinRandomAccessList::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn cons(&self, x: T) -> Self {
+        self.cons(x)
+    }
+
+    fn try_head(&self) -> Option<&T> {
+        self.try_head()
+    }
+
+    fn try_tail(&self) -> Option<Self> {
+        self.try_tail()
+    }
+
+    fn uncons(&self) -> Option<(&T, Self)> {
+        self.uncons()
+    }
+}
+
+impl<T> traits::RandomAccessList<T> for SkewBinRandomAccessList<T>
+where T: Clone + Debug {
+    fn try_lookup(&self, i: usize) -> Option<&T> {
+        self.try_lookup(i)
+    }
+
+    fn try_update(&self, i: usize, x: T) -> Option<Self> {
+        self.try_update(i, x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weights<T: Clone + Debug>(l: &SkewBinRandomAccessList<T>) -> Vec<usize> {
+        l.trees.iter().map(|&(w, _)| w).collect()
+    }
+
+    // Weights are 2^k - 1 and increasing except that the first two can be the same
+    fn check_invariant<T: Clone + Debug>(l: &SkewBinRandomAccessList<T>) {
+        let ws = weights(l);
+        for (i, &w) in ws.iter().enumerate() {
+            assert!((w + 1).is_power_of_two(), "weight {} in {:?}", w, ws);
+            if i > 0 {
+                assert!(ws[i - 1] < w || i == 1 && ws[0] == w, "weights {:?}", ws);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invariant() {
+        let mut l = SkewBinRandomAccessList::empty();
+        for i in 0..300 {
+            l = l.cons(i);
+            check_invariant(&l);
+            if i % 3 == 0 {
+                l = l.tail();
+                check_invariant(&l);
+            }
+        }
+        check_invariant(&(0..100).collect::<SkewBinRandomAccessList<_>>());
+    }
+
+    #[test]
+    fn test_drop() {
+        for n in 0..70 {
+            let l = (0..n).collect::<SkewBinRandomAccessList<_>>();
+            for k in 0..n + 2 {
+                let d = l.drop(k);
+                check_invariant(&d);
+                assert_eq!(d.len(), n.saturating_sub(k));
+                assert!(d.iter().cloned().eq(k.min(n)..n), "n={} k={}", n, k);
+                // The result is the same as dropping by tail
+                let t = (0..k.min(n)).fold(l.clone(), |l, _| l.tail());
+                assert_eq!(weights(&d), weights(&t));
+            }
+        }
+    }
+}
@@ -0,0 +1,326 @@
+// Section 5.4
+//
+// Splay heap.
+// Amortized O(log n) insert, merge, find_min and delete_min (if old versions are not reused)
+
+use std::fmt::Debug;
+use std::cmp::Ord;
+use std::mem;
+use ptr::Ptr;
+use traits;
+
+// Binary search tree which is restructured on each access. Duplicate elements are allowed.
+// Each node caches the number of elements in it so that `partition` can return sizes of both sides.
+#[derive(Debug, Clone)]
+enum Node<T: Ord + Clone + Debug> {
+    Leaf,
+    Knot(usize, Link<T>, T, Link<T>),
+}
+
+type Link<T> = Ptr<Node<T>>;
+
+// How to wrap results of the next step of `partition` on each side
+type Frame<T> = (Option<(Link<T>, T)>, Option<(T, Link<T>)>);
+
+impl<T> Node<T>
+where T: Ord + Clone + Debug {
+    fn size(&self) -> usize {
+        match *self {
+            Node::Leaf => 0,
+            Node::Knot(s, _, _, _) => s,
+        }
+    }
+
+    fn knot(a: Link<T>, x: T, b: Link<T>) -> Link<T> {
+        Ptr::new(Node::Knot(a.size() + b.size() + 1, a, x, b))
+    }
+
+    // Splits the tree into elements <= pivot and elements > pivot. Two levels are walked at once and
+    // the path is rotated so that its depth is roughly halved.
+    // The book's definition is recursive. Here each step records how to wrap results of the next step
+    // so that it doesn't overflow the stack even when the tree is a long path.
+    fn partition(t: &Link<T>, pivot: &T) -> (Link<T>, Link<T>) {
+        // (left child, element) makes smaller = T(left, element, smaller).
+        // (element, right child) makes bigger = T(bigger, element, right).
+        let mut frames: Vec<Frame<T>> = vec![];
+        let mut t = t.clone();
+        let (mut small, mut big) = loop {
+            let next = match *t {
+                Node::Leaf => break (t.clone(), t.clone()),
+                Node::Knot(_, ref a, ref x, ref b) if x <= pivot => match **b {
+                    Node::Leaf => break (t.clone(), b.clone()),
+                    Node::Knot(_, ref b1, ref y, ref b2) if y <= pivot => {
+                        frames.push((Some((Node::knot(a.clone(), x.clone(), b1.clone()), y.clone())), None));
+                        b2.clone()
+                    },
+                    Node::Knot(_, ref b1, ref y, ref b2) => {
+                        frames.push((Some((a.clone(), x.clone())), Some((y.clone(), b2.clone()))));
+                        b1.clone()
+                    },
+                },
+                Node::Knot(_, ref a, ref x, ref b) => match **a {
+                    Node::Leaf => break (a.clone(), t.clone()),
+                    Node::Knot(_, ref a1, ref y, ref a2) if y <= pivot => {
+                        frames.push((Some((a1.clone(), y.clone())), Some((x.clone(), b.clone()))));
+                        a2.clone()
+                    },
+                    Node::Knot(_, ref a1, ref y, ref a2) => {
+                        frames.push((None, Some((y.clone(), Node::knot(a2.clone(), x.clone(), b.clone())))));
+                        a1.clone()
+                    },
+                },
+            };
+            t = next;
+        };
+        while let Some((s, b)) = frames.pop() {
+            if let Some((l, x)) = s {
+                small = Node::knot(l, x, small);
+            }
+            if let Some((x, r)) = b {
+                big = Node::knot(big, x, r);
+            }
+        }
+        (small, big)
+    }
+
+    fn merge(t1: &Link<T>, t2: &Link<T>) -> Link<T> {
+        match **t1 {
+            Node::Leaf => t2.clone(),
+            Node::Knot(_, ref a, ref x, ref b) => {
+                let (ta, tb) = Node::partition(t2, x);
+                Node::knot(Node::merge(&ta, a), x.clone(), Node::merge(&tb, b))
+            },
+        }
+    }
+
+    fn find_min(&self) -> Option<&T> {
+        let mut min = None;
+        let mut t = self;
+        while let Node::Knot(_, ref a, ref x, _) = *t {
+            min = Some(x);
+            t = a;
+        }
+        min
+    }
+
+    // The left path is rotated while walking down to the minimum element. Written with a loop for the
+    // same reason as `partition`.
+    fn delete_min(t: &Link<T>) -> Option<Link<T>> {
+        // (element, right child) makes deleted = T(deleted, element, right)
+        let mut frames = vec![];
+        let mut t = t;
+        let mut deleted = loop {
+            match **t {
+                Node::Leaf => return None,
+                Node::Knot(_, ref l, ref y, ref c) => match **l {
+                    Node::Leaf => break c.clone(),
+                    Node::Knot(_, ref a, ref x, ref b) => {
+                        let r = Node::knot(b.clone(), y.clone(), c.clone());
+                        if let Node::Leaf = **a {
+                            break r;
+                        }
+                        frames.push((x.clone(), r));
+                        t = a;
+                    },
+                },
+            }
+        };
+        while let Some((x, r)) = frames.pop() {
+            deleted = Node::knot(deleted, x, r);
+        }
+        Some(deleted)
+    }
+
+    // In-order traversal
+    fn to_vec(t: &Link<T>) -> Vec<T> {
+        let mut v = Vec::with_capacity(t.size());
+        let mut stack = vec![];
+        let mut t = t;
+        loop {
+            while let Node::Knot(_, ref a, _, _) = **t {
+                stack.push(t);
+                t = a;
+            }
+            match stack.pop() {
+                Some(n) => match **n {
+                    Node::Knot(_, _, ref x, ref b) => {
+                        v.push(x.clone());
+                        t = b;
+                    },
+                    Node::Leaf => unreachable!("only knots are pushed"),
+                },
+                None => return v,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SplayHeap<T: Ord + Clone + Debug> {
+    root: Link<T>,
+}
+
+impl<T> SplayHeap<T>
+where T: Ord + Clone + Debug {
+    pub fn empty() -> Self {
+        SplayHeap{root: Ptr::new(Node::Leaf)}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.size() == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.root.size()
+    }
+
+    pub fn merge(&self, rhs: &Self) -> Self {
+        SplayHeap{root: Node::merge(&self.root, &rhs.root)}
+    }
+
+    pub fn insert(&self, v: T) -> Self {
+        let (a, b) = Node::partition(&self.root, &v);
+        SplayHeap{root: Node::knot(a, v, b)}
+    }
+
+    // Splits the heap into a heap of elements <= pivot and a heap of the others
+    pub fn partition(&self, pivot: &T) -> (Self, Self) {
+        let (a, b) = Node::partition(&self.root, pivot);
+        (SplayHeap{root: a}, SplayHeap{root: b})
+    }
+
+    pub fn pop_min(&self) -> Option<(&T, Self)> {
+        self.try_find_min().and_then(|x| self.try_delete_min().map(|h| (x, h)))
+    }
+
+    // Not O(1) unlike other heaps. The minimum element is the leftmost one.
+    pub fn try_find_min(&self) -> Option<&T> {
+        self.root.find_min()
+    }
+
+    pub fn try_delete_min(&self) -> Option<Self> {
+        Node::delete_min(&self.root).map(|root| SplayHeap{root})
+    }
+
+    pub fn find_min(&self) -> &T {
+        self.try_find_min().expect("heap is empty!")
+    }
+
+    pub fn delete_min(&self) -> Self {
+        self.try_delete_min().expect("heap is empty!")
+    }
+
+    // Iterates elements in ascending order
+    pub fn iter(&self) -> traits::HeapIter<T, Self> {
+        traits::HeapIter::new(self.clone())
+    }
+
+    pub fn from_slice(a: &[T]) -> Self {
+        a.iter().fold(SplayHeap::empty(), |h, x| h.insert(x.clone()))
+    }
+
+    // Exercise 5.7: Sorts elements by inserting them to a splay tree and traversing it in order.
+    // It takes O(n) when the elements are already sorted (or reversed).
+    pub fn sort(a: &[T]) -> Vec<T> {
+        Node::to_vec(&SplayHeap::from_slice(a).root)
+    }
+}
+
+// A splay tree can be a long path (e.g. after inserting sorted elements) and dropping it recursively
+// overflows the stack. Nodes owned only by this heap are unlinked in a loop as `List` does.
+impl<T> Drop for SplayHeap<T>
+where T: Ord + Clone + Debug {
+    fn drop(&mut self) {
+        let mut links = vec![];
+        let mut cur = Ptr::get_mut(&mut self.root).map(|n| mem::replace(n, Node::Leaf));
+        while let Some(n) = cur {
+            if let Node::Knot(_, a, _, b) = n {
+                links.push(a);
+                links.push(b);
+            }
+            cur = None;
+            while let Some(mut l) = links.pop() {
+                if let Some(n) = Ptr::get_mut(&mut l) {
+                    cur = Some(mem::replace(n, Node::Leaf));
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl<T> traits::Heap<T> for SplayHeap<T>
+where T: Ord + Clone + Debug {
+    fn empty() -> Self {
+        SplayHeap::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn insert(&self, x: T) -> Self {
+        self.insert(x)
+    }
+
+    fn merge(&self, other: &Self) -> Self {
+        self.merge(other)
+    }
+
+    fn try_find_min(&self) -> Option<&T> {
+        self.try_find_min()
+    }
+
+    fn try_delete_min(&self) -> Option<Self> {
+        self.try_delete_min()
+    }
+
+    fn pop_min(&self) -> Option<(&T, Self)> {
+        self.pop_min()
+    }
+}
+
+impl<T> traits::HeapFamily for SplayHeap<T>
+where T: Ord + Clone + Debug {
+    type Heap<E: Clone + Ord + Debug> = SplayHeap<E>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition() {
+        let h = SplayHeap::from_slice(&[5, 3, 8, 1, 9, 3, 7]);
+        let (small, big) = h.partition(&3);
+        assert_eq!(small.len(), 3);
+        assert_eq!(big.len(), 4);
+        assert_eq!(small.iter().collect::<Vec<_>>(), vec![1, 3, 3]);
+        assert_eq!(big.iter().collect::<Vec<_>>(), vec![5, 7, 8, 9]);
+        let (small, big) = h.partition(&0);
+        assert!(small.is_empty());
+        assert_eq!(big.len(), 7);
+    }
+
+    #[test]
+    fn test_sort() {
+        let xs = (0..1000).map(|i| i * 37 % 101).collect::<Vec<_>>();
+        let mut sorted = xs.clone();
+        sorted.sort();
+        assert_eq!(SplayHeap::sort(&xs), sorted);
+        assert_eq!(SplayHeap::<i32>::sort(&[]), vec![]);
+    }
+
+    #[test]
+    fn test_long_path() {
+        // Sorted insertion makes a path. Deleting from it must not overflow the stack.
+        let n = 100000;
+        let mut h = (0..n).fold(SplayHeap::empty(), |h, i| h.insert(i));
+        assert_eq!(SplayHeap::sort(&(0..n).rev().collect::<Vec<_>>()), (0..n).collect::<Vec<_>>());
+        for i in 0..n {
+            assert_eq!(h.find_min(), &i);
+            h = h.delete_min();
+        }
+        assert!(h.is_empty());
+    }
+}
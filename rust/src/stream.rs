@@ -3,92 +3,274 @@
 // Stream: Lazyily evaluated list
 
 use std::fmt::Debug;
+use std::iter::FromIterator;
 use lazy::Delayed;
+use ptr::Shareable;
 
 #[derive(Debug, Clone)]
-enum StreamCell<'a, T: 'a + Clone + Debug> {
+pub enum StreamCell<'a, T: 'a + Clone + Debug + Shareable> {
     Nil,
     Cons(T, Cell<'a, T>),
 }
 
-type Cell<'a, T> = Delayed<'a, StreamCell<'a, T>>;
+pub type Cell<'a, T> = Delayed<'a, StreamCell<'a, T>>;
+
+// Dropping a cell recursively drops the rest of cells and overflows the stack on a long stream. Here
+// forced cells which are owned only by this cell are unlinked one by one in a loop instead.
+impl<'a, T> Drop for StreamCell<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn drop(&mut self) {
+        let mut next = match *self {
+            StreamCell::Cons(_, ref mut xs) => xs.take_unique(),
+            StreamCell::Nil => return,
+        };
+        while let Some(StreamCell::Cons(_, ref mut xs)) = next {
+            next = xs.take_unique();
+        }
+    }
+}
 
 use self::StreamCell::*;
 
+// Note:
+// Cells are exposed so that other lazy data structures can define their own incremental operations
+// on streams (e.g. `rotate` of real-time queue).
+// Operations take `&self` and clone cells (only pointers) into delayed expressions so that the
+// result does not borrow its operands. It makes streams usable as fields of other data structures.
 #[derive(Debug, Clone)]
-pub struct Stream<'a, T: 'a + Clone + Debug>(Cell<'a, T>);
+pub struct Stream<'a, T: 'a + Clone + Debug + Shareable>(pub Cell<'a, T>);
 
 impl<'a, T> Stream<'a, T>
-where T: 'a + Clone + Debug {
-    pub fn cons(&'a self, d: &'a Delayed<'a, T>) -> Self {
-        Stream(lazily!{
-            Cons(d.eval().clone(), self.0.clone())
-        })
+where T: 'a + Clone + Debug + Shareable {
+    pub fn empty() -> Self {
+        Stream(Delayed::constant(Nil))
+    }
+
+    // Forces the first cell
+    pub fn is_empty(&self) -> bool {
+        matches!(*self.0.eval(), Nil)
+    }
+
+    pub fn cons(&self, x: T) -> Self {
+        Stream(Delayed::constant(Cons(x, self.0.clone())))
+    }
+
+    pub fn uncons(&self) -> Option<(&T, Self)> {
+        match *self.0.eval() {
+            Nil => None,
+            Cons(ref x, ref xs) => Some((x, Stream(xs.clone()))),
+        }
+    }
+
+    pub fn try_head(&self) -> Option<&T> {
+        self.uncons().map(|(x, _)| x)
     }
 
-    fn concat_impl(lhs: &'a Cell<'a, T>, rhs: &'a Cell<'a, T>) -> Cell<'a, T> {
+    pub fn try_tail(&self) -> Option<Self> {
+        self.uncons().map(|(_, xs)| xs)
+    }
+
+    pub fn head(&self) -> &T {
+        self.try_head().expect("Stream is empty!")
+    }
+
+    pub fn tail(&self) -> Self {
+        self.try_tail().expect("Stream is empty!")
+    }
+
+    // Incremental. Forcing each cell of the result forces only one cell of `lhs`.
+    fn concat_impl(lhs: &Cell<'a, T>, rhs: Cell<'a, T>) -> StreamCell<'a, T> {
         match *lhs.eval() {
-            Nil => rhs.clone(),
-            Cons(ref x, ref xs) => lazily!{
-                Cons(x.clone(), Stream::concat_impl(xs, rhs))
+            Nil => rhs.eval().clone(),
+            Cons(ref x, ref xs) => {
+                let xs = xs.clone();
+                Cons(x.clone(), lazily!{
+                    Stream::concat_impl(&xs, rhs)
+                })
             },
         }
     }
-    pub fn concat(&'a self, other: &'a Self) -> Self {
-        Stream(Stream::concat_impl(&self.0, &other.0))
+    pub fn concat(&self, other: &Self) -> Self {
+        let (lhs, rhs) = (self.0.clone(), other.0.clone());
+        Stream(lazily!{
+            Stream::concat_impl(&lhs, rhs)
+        })
     }
 
-    fn take_impl(s: &'a Cell<'a, T>, u: usize) -> Cell<'a, T> {
+    // Incremental
+    fn take_impl(s: &Cell<'a, T>, u: usize) -> StreamCell<'a, T> {
         if u == 0 {
-            return s.clone();
+            return Nil;
         }
         match *s.eval() {
-            Nil => Delayed::constant(Nil),
-            Cons(ref x, ref xs) => lazily!{
-                Cons(x.clone(), Stream::take_impl(xs, u-1))
+            Nil => Nil,
+            Cons(ref x, ref xs) => {
+                let xs = xs.clone();
+                Cons(x.clone(), lazily!{
+                    Stream::take_impl(&xs, u-1)
+                })
             },
         }
     }
-    pub fn take(&'a self, u: usize) -> Self {
-        Stream(Stream::take_impl(&self.0, u))
+    pub fn take(&self, u: usize) -> Self {
+        let s = self.0.clone();
+        Stream(lazily!{
+            Stream::take_impl(&s, u)
+        })
     }
 
-    fn drop_impl(xs: &'a Cell<'a, T>, u: usize) -> Cell<'a, T> {
-        if u == 0 {
-            return xs.clone();
-        }
-        match *xs.eval() {
-            Nil => Delayed::constant(Nil),
-            Cons(_, ref ys) => Stream::drop_impl(ys, u-1),
+    // Monolithic. Forcing the first cell of the result forces `u` cells of the stream.
+    fn drop_impl(xs: &Cell<'a, T>, u: usize) -> StreamCell<'a, T> {
+        let mut cur = xs.clone();
+        for _ in 0..u {
+            let next = match *cur.eval() {
+                Nil => return Nil,
+                Cons(_, ref ys) => ys.clone(),
+            };
+            cur = next;
         }
+        let cell = cur.eval().clone();
+        cell
     }
-    pub fn drop(&'a self, u: usize) -> Self {
+    pub fn drop(&self, u: usize) -> Self {
+        let s = self.0.clone();
         Stream(lazily!{
-            Stream::drop_impl(&self.0, u).eval().clone()
+            Stream::drop_impl(&s, u)
         })
     }
 
-    fn reverse_impl(acc: Cell<'a, T>, xs: &'a Cell<'a, T>) -> Cell<'a, T> {
-        match *xs.eval() {
-            Nil => acc,
-            Cons(ref y, ref ys) => Stream::reverse_impl(lazily!{
-                Cons(y.clone(), acc.clone()/*Cannot move variables out of the captured lambda...*/)
-            }, ys),
+    // Monolithic. Forcing the first cell of the result forces whole of the stream.
+    fn reverse_impl(xs: &Cell<'a, T>) -> StreamCell<'a, T> {
+        let mut acc = Nil;
+        let mut cur = xs.clone();
+        loop {
+            let next = match *cur.eval() {
+                Nil => return acc,
+                Cons(ref y, ref ys) => {
+                    acc = Cons(y.clone(), Delayed::constant(acc));
+                    ys.clone()
+                },
+            };
+            cur = next;
         }
     }
-    pub fn reverse(&'a self) -> Self {
+    pub fn reverse(&self) -> Self {
+        let s = self.0.clone();
         Stream(lazily!{
-            Stream::reverse_impl(Delayed::constant(Nil), &self.0).eval().clone()
+            Stream::reverse_impl(&s)
         })
     }
+
+    // Takes elements out of the cells which are owned only by this stream, from the head until a
+    // shared cell. Unforced cells are forced. It is used for dropping streams of nested data structures
+    // in a loop.
+    pub fn into_unique_elems(mut self) -> Vec<T> {
+        let mut elems = vec![];
+        let mut next = self.0.force_unique();
+        while let Some(Cons(ref x, ref mut xs)) = next {
+            elems.push(x.clone());
+            next = xs.force_unique();
+        }
+        elems
+    }
+
+    // Forces all cells
+    pub fn iter(&self) -> Iter<'_, 'a, T> {
+        Iter{cur: &self.0}
+    }
+}
+
+pub struct Iter<'s, 'a: 's, T: 'a + Clone + Debug + Shareable> {
+    cur: &'s Cell<'a, T>,
 }
 
-#[cfg(tests)]
+impl<'s, 'a, T> Iterator for Iter<'s, 'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    type Item = &'s T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match *self.cur.eval() {
+            Nil => None,
+            Cons(ref x, ref xs) => {
+                self.cur = xs;
+                Some(x)
+            },
+        }
+    }
+}
+
+impl<'a, T> FromIterator<T> for Stream<'a, T>
+where T: 'a + Clone + Debug + Shareable {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let v = iter.into_iter().collect::<Vec<_>>();
+        v.into_iter().rev().fold(Stream::empty(), |s, x| s.cons(x))
+    }
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
 
+    fn to_vec(s: &Stream<i32>) -> Vec<i32> {
+        s.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_cons() {
+        let s = Stream::empty();
+        assert!(s.is_empty());
+        assert!(s.uncons().is_none());
+        let s = s.cons(2).cons(1);
+        assert!(!s.is_empty());
+        assert_eq!(s.head(), &1);
+        assert_eq!(s.tail().head(), &2);
+        assert!(s.tail().tail().is_empty());
+        assert_eq!(to_vec(&(1..5).collect()), vec![1, 2, 3, 4]);
+    }
+
     #[test]
     fn test_concat() {
-        // TODO
+        let s = (1..4).collect::<Stream<_>>();
+        let t = (4..6).collect::<Stream<_>>();
+        assert_eq!(to_vec(&s.concat(&t)), vec![1, 2, 3, 4, 5]);
+        assert_eq!(to_vec(&s.concat(&Stream::empty())), vec![1, 2, 3]);
+        assert_eq!(to_vec(&Stream::empty().concat(&t)), vec![4, 5]);
+        // Operands are not changed
+        assert_eq!(to_vec(&s), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_take_drop() {
+        let s = (1..6).collect::<Stream<_>>();
+        assert_eq!(to_vec(&s.take(0)), vec![]);
+        assert_eq!(to_vec(&s.take(3)), vec![1, 2, 3]);
+        assert_eq!(to_vec(&s.take(10)), vec![1, 2, 3, 4, 5]);
+        assert_eq!(to_vec(&s.drop(0)), vec![1, 2, 3, 4, 5]);
+        assert_eq!(to_vec(&s.drop(3)), vec![4, 5]);
+        assert_eq!(to_vec(&s.drop(10)), vec![]);
+    }
+
+    #[test]
+    fn test_reverse() {
+        let s = (1..6).collect::<Stream<_>>();
+        assert_eq!(to_vec(&s.reverse()), vec![5, 4, 3, 2, 1]);
+        assert!(Stream::<i32>::empty().reverse().is_empty());
+    }
+
+    #[test]
+    fn test_long() {
+        // Dropping a long stream doesn't overflow the stack
+        let s = (0..1000000).collect::<Stream<_>>();
+        assert_eq!(s.reverse().head(), &999999);
+        assert_eq!(s.concat(&s).drop(1500000).head(), &500000);
+    }
+
+    #[test]
+    fn test_laziness() {
+        let s = Stream::empty().cons(1).concat(&Stream(lazily!{
+            panic!("never evaluated")
+        }));
+        assert_eq!(s.head(), &1);
+        let _ = s.tail().take(3).drop(1).reverse();
     }
 } // mod tests
@@ -0,0 +1,565 @@
+// Signatures shared by data structures in this crate
+//
+// They correspond to ML signatures in the book (STACK, QUEUE, DEQUE, HEAP and SET). Each structure
+// implements its signature by delegating to its inherent methods so that code can be generic over
+// implementations (e.g. `fn f<Q: Queue<i32>>(q: Q)`).
+//
+// Accessors which can fail on an empty structure are required in `try_*` form returning `Option`.
+// Panicking versions are provided as thin wrappers of them.
+
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+pub trait Stack<T>: Sized {
+    fn empty() -> Self;
+    fn is_empty(&self) -> bool;
+    fn cons(&self, x: T) -> Self;
+    fn try_head(&self) -> Option<&T>;
+    fn try_tail(&self) -> Option<Self>;
+
+    fn head(&self) -> &T {
+        self.try_head().expect("Stack is empty!")
+    }
+
+    fn tail(&self) -> Self {
+        self.try_tail().expect("Stack is empty!")
+    }
+
+    fn uncons(&self) -> Option<(&T, Self)> {
+        self.try_head().and_then(|x| self.try_tail().map(|xs| (x, xs)))
+    }
+}
+
+pub trait Queue<T>: Sized {
+    fn empty() -> Self;
+    fn is_empty(&self) -> bool;
+    fn enq(&self, x: T) -> Self;
+    fn try_top(&self) -> Option<&T>;
+    fn try_deq(&self) -> Option<Self>;
+
+    fn top(&self) -> &T {
+        self.try_top().expect("Queue is empty!")
+    }
+
+    fn deq(&self) -> Self {
+        self.try_deq().expect("Queue is empty!")
+    }
+
+    fn pop(&self) -> Option<(&T, Self)> {
+        self.try_top().and_then(|x| self.try_deq().map(|q| (x, q)))
+    }
+}
+
+pub trait Deque<T>: Sized {
+    fn empty() -> Self;
+    fn is_empty(&self) -> bool;
+    fn enq_front(&self, x: T) -> Self;
+    fn enq_back(&self, x: T) -> Self;
+    fn try_front(&self) -> Option<&T>;
+    fn try_back(&self) -> Option<&T>;
+    fn try_deq_front(&self) -> Option<Self>;
+    fn try_deq_back(&self) -> Option<Self>;
+
+    fn front(&self) -> &T {
+        self.try_front().expect("Deque is empty!")
+    }
+
+    fn back(&self) -> &T {
+        self.try_back().expect("Deque is empty!")
+    }
+
+    fn deq_front(&self) -> Self {
+        self.try_deq_front().expect("Deque is empty!")
+    }
+
+    fn deq_back(&self) -> Self {
+        self.try_deq_back().expect("Deque is empty!")
+    }
+
+    fn pop_front(&self) -> Option<(&T, Self)> {
+        self.try_front().and_then(|x| self.try_deq_front().map(|d| (x, d)))
+    }
+
+    fn pop_back(&self) -> Option<(&T, Self)> {
+        self.try_back().and_then(|x| self.try_deq_back().map(|d| (x, d)))
+    }
+}
+
+// Indices start from the head of the list
+pub trait RandomAccessList<T>: Stack<T> {
+    fn try_lookup(&self, i: usize) -> Option<&T>;
+    fn try_update(&self, i: usize, x: T) -> Option<Self>;
+
+    fn lookup(&self, i: usize) -> &T {
+        self.try_lookup(i).expect("Index out of bounds!")
+    }
+
+    fn update(&self, i: usize, x: T) -> Self {
+        self.try_update(i, x).expect("Index out of bounds!")
+    }
+}
+
+pub trait Heap<T: Ord>: Sized {
+    fn empty() -> Self;
+    fn is_empty(&self) -> bool;
+    fn insert(&self, x: T) -> Self;
+    fn merge(&self, other: &Self) -> Self;
+    fn try_find_min(&self) -> Option<&T>;
+    fn try_delete_min(&self) -> Option<Self>;
+
+    fn find_min(&self) -> &T {
+        self.try_find_min().expect("heap is empty!")
+    }
+
+    fn delete_min(&self) -> Self {
+        self.try_delete_min().expect("heap is empty!")
+    }
+
+    fn pop_min(&self) -> Option<(&T, Self)> {
+        self.try_find_min().and_then(|x| self.try_delete_min().map(|h| (x, h)))
+    }
+}
+
+// Heap implementation which can be instantiated with any element type, like ML functors taking a HEAP
+// structure. It is implemented by each heap for any of its instances so that `BinHeap<T>` can be
+// passed to parameterize another structure by binomial heaps. Such a parameter is only a tag choosing
+// the implementation: its element type is ignored and the structure never holds a value of it.
+pub trait HeapFamily {
+    type Heap<E: Clone + Ord + Debug>: Heap<E> + Clone + Debug;
+}
+
+// Iterates elements of a heap in ascending order. Each step deletes the minimum element from the
+// iterator's own version of the heap, so the original heap is not changed.
+pub struct HeapIter<T, H> {
+    heap: H,
+    elem: PhantomData<T>,
+}
+
+impl<T, H> HeapIter<T, H>
+where T: Ord, H: Heap<T> {
+    pub fn new(heap: H) -> Self {
+        HeapIter{heap, elem: PhantomData}
+    }
+}
+
+impl<T, H> Iterator for HeapIter<T, H>
+where T: Ord + Clone, H: Heap<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (x, h) = match self.heap.pop_min() {
+            Some((x, h)) => (x.clone(), h),
+            None => return None,
+        };
+        self.heap = h;
+        Some(x)
+    }
+}
+
+pub trait Set<T>: Sized {
+    fn empty() -> Self;
+    fn insert(&self, x: T) -> Self;
+    fn member(&self, x: &T) -> bool;
+}
+
+// Generic tests which every implementation must pass. Each module's own unit tests remain, and the
+// scenarios of the unit tests which need only the signatures are run through the traits as well.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Reverse;
+    use std::collections::{BinaryHeap, VecDeque};
+    use list::List;
+    use catlist::CatList;
+    use binrandomaccesslist::BinRandomAccessList;
+    use skewbinrandomaccesslist::SkewBinRandomAccessList;
+    use queue;
+    use bankersqueue::BankersQueue;
+    use physicistsqueue::PhysicistsQueue;
+    use realtimequeue::RealTimeQueue;
+    use hoodmelvillequeue::HoodMelvilleQueue;
+    use deque;
+    use bankersdeque::BankersDeque;
+    use realtimedeque::RealTimeDeque;
+    use catdeque::{SimpleCatDeque, CatDeque};
+    use leftheap::LeftHeap;
+    use binheap::{BinHeap, BinHeap2};
+    use skewbinheap::SkewBinHeap;
+    use bootstrappedheap::BootstrappedHeap;
+    use splayheap::SplayHeap;
+    use pairingheap::{PairingHeap, LazyPairingHeap};
+    use weightleftheap::WeightLeftHeap;
+    use bintree::BinTree;
+    use rbtree::RBTree;
+
+    fn check_stack<S: Stack<i32>>() {
+        let s = S::empty();
+        assert!(s.is_empty());
+        assert!(s.try_head().is_none());
+        assert!(s.try_tail().is_none());
+        assert!(s.uncons().is_none());
+        let s = s.cons(1).cons(2);
+        assert!(!s.is_empty());
+        assert_eq!(s.head(), &2);
+        assert_eq!(s.tail().head(), &1);
+        assert!(s.tail().tail().is_empty());
+        let (x, s) = s.uncons().unwrap();
+        assert_eq!(x, &2);
+        assert_eq!(s.try_head(), Some(&1));
+    }
+
+    fn check_random_access_list<L: RandomAccessList<i32> + Clone>() {
+        let l = L::empty();
+        assert!(l.try_lookup(0).is_none());
+        assert!(l.try_update(0, 1).is_none());
+        let l = (0..10).fold(l, |l, i| l.cons(i));
+        assert_eq!(l.head(), &9);
+        assert_eq!(l.lookup(0), &9);
+        assert_eq!(l.lookup(9), &0);
+        assert!(l.try_lookup(10).is_none());
+        let l2 = l.update(3, 42);
+        assert_eq!(l2.lookup(3), &42);
+        assert_eq!(l.lookup(3), &6);
+        assert_eq!(l2.tail().lookup(2), &42);
+        assert!(l2.try_update(10, 0).is_none());
+
+        let l = (0..100).rev().fold(L::empty(), |l, i| l.cons(i));
+        for i in 0..100 {
+            assert_eq!(l.lookup(i as usize), &i);
+        }
+        assert!(l.try_lookup(100).is_none());
+        assert!(l.try_update(100, 0).is_none());
+        let l2 = (0..100).step_by(3).fold(l.clone(), |l, i| l.update(i as usize, i * 10));
+        for i in 0..100 {
+            assert_eq!(l2.lookup(i as usize), &if i % 3 == 0 { i * 10 } else { i });
+            // Original version is not changed
+            assert_eq!(l.lookup(i as usize), &i);
+        }
+    }
+
+    // Compares the list with Vec while consing and taking tails. Every length has a different shape of
+    // trees.
+    fn check_random_access_list_model<L: RandomAccessList<i32>>() {
+        let mut l = L::empty();
+        let mut v = vec![];
+        for i in 0..300 {
+            l = l.cons(i);
+            v.insert(0, i);
+            if i % 3 == 0 {
+                l = l.tail();
+                v.remove(0);
+            }
+            for (j, x) in v.iter().enumerate() {
+                assert_eq!(l.lookup(j), x);
+            }
+            assert!(l.try_lookup(v.len()).is_none());
+        }
+        while let Some((x, rest)) = l.uncons() {
+            assert_eq!(x, &v.remove(0));
+            l = rest;
+        }
+        assert!(v.is_empty());
+    }
+
+    fn check_queue<Q: Queue<i32>>() {
+        let q = Q::empty();
+        assert!(q.is_empty());
+        assert!(q.try_top().is_none());
+        assert!(q.try_deq().is_none());
+        assert!(q.pop().is_none());
+        let q = q.enq(1).enq(2).enq(3);
+        let (x, q2) = q.pop().unwrap();
+        assert_eq!(x, &1);
+        assert_eq!(q2.try_top(), Some(&2));
+        let q = q.deq();
+        assert_eq!(q.top(), &2);
+        let q = q.enq(4).deq().deq();
+        assert_eq!(q.top(), &4);
+        let q = q.deq();
+        assert!(q.is_empty());
+    }
+
+    fn check_deque<D: Deque<i32>>() {
+        let d = D::empty();
+        assert!(d.is_empty());
+        assert!(d.try_front().is_none());
+        assert!(d.try_back().is_none());
+        assert!(d.try_deq_front().is_none());
+        assert!(d.try_deq_back().is_none());
+        assert!(d.pop_front().is_none());
+        assert!(d.pop_back().is_none());
+        let d = d.enq_front(2).enq_back(3).enq_front(1).enq_back(4);
+        assert_eq!(d.front(), &1);
+        assert_eq!(d.back(), &4);
+        assert_eq!(d.pop_front().unwrap().1.try_front(), Some(&2));
+        assert_eq!(d.pop_back().unwrap().1.try_back(), Some(&3));
+        let d = d.deq_back();
+        assert_eq!(d.back(), &3);
+        let d = d.deq_front();
+        assert_eq!(d.front(), &2);
+        let d = d.deq_front().deq_back();
+        assert!(d.is_empty());
+
+        check_deque_front_to_back::<D>();
+        check_deque_back_to_front::<D>();
+        check_deque_enq_both_sides::<D>();
+    }
+
+    // Compares the deque with VecDeque through random operations on both ends. `d` is an empty deque.
+    fn check_deque_model<D: Deque<i32>>(mut d: D) {
+        let mut v = VecDeque::new();
+        for i in 0..2000 {
+            match i * 7 % 11 {
+                0..=2 => {
+                    d = d.enq_front(i);
+                    v.push_front(i);
+                },
+                3..=5 => {
+                    d = d.enq_back(i);
+                    v.push_back(i);
+                },
+                6 | 7 => {
+                    d = d.try_deq_front().unwrap_or(d);
+                    v.pop_front();
+                },
+                _ => {
+                    d = d.try_deq_back().unwrap_or(d);
+                    v.pop_back();
+                },
+            }
+            assert_eq!(d.try_front(), v.front());
+            assert_eq!(d.try_back(), v.back());
+        }
+        while let Some((x, rest)) = d.pop_front() {
+            assert_eq!(Some(*x), v.pop_front());
+            d = rest;
+        }
+        assert!(v.is_empty());
+    }
+
+    // Unit tests of deque.rs
+    fn check_deque_front_to_back<D: Deque<i32>>() {
+        let d = D::empty().enq_front(1);
+        assert_eq!(d.front(), &1);
+        assert_eq!(d.back(), &1);
+
+        let d = d.enq_front(2).enq_front(3);
+        assert_eq!(d.front(), &3);
+        assert_eq!(d.back(), &1);
+
+        let d = d.deq_back();
+        assert_eq!(d.front(), &3);
+        assert_eq!(d.back(), &2);
+
+        let d = d.deq_back().deq_back();
+        assert!(d.is_empty());
+    }
+
+    fn check_deque_back_to_front<D: Deque<i32>>() {
+        let d = D::empty().enq_back(1);
+        assert_eq!(d.front(), &1);
+        assert_eq!(d.back(), &1);
+
+        let d = d.enq_back(2).enq_back(3);
+        assert_eq!(d.front(), &1);
+        assert_eq!(d.back(), &3);
+
+        let d = d.deq_front();
+        assert_eq!(d.front(), &2);
+        assert_eq!(d.back(), &3);
+
+        let d = d.deq_front().deq_front();
+        assert!(d.is_empty());
+    }
+
+    fn check_deque_enq_both_sides<D: Deque<i32>>() {
+        let d = D::empty().enq_front(2).enq_back(3).enq_front(1).enq_back(4);
+        let d = d.deq_back().deq_front().deq_back().deq_front();
+        assert!(d.is_empty());
+
+        let d = D::empty().enq_front(2).enq_back(3).enq_front(1).enq_back(4);
+        let d = d.deq_front().deq_back().deq_front().deq_back();
+        assert!(d.is_empty());
+    }
+
+    fn check_heap<H: Heap<i32>>() {
+        let e = H::empty();
+        assert!(e.is_empty());
+        assert!(e.merge(&e).is_empty());
+        assert!(e.try_find_min().is_none());
+        assert!(e.try_delete_min().is_none());
+        assert!(e.pop_min().is_none());
+        let h1 = e.insert(4).insert(6).insert(10);
+        let h2 = e.insert(1).insert(7).insert(9);
+        let mut h = h1.merge(&h2);
+        for i in &[1, 4, 6, 7, 9, 10] {
+            assert_eq!(h.find_min(), i);
+            h = h.delete_min();
+        }
+        assert!(h.is_empty());
+        h = h1.merge(&h2);
+        for i in &[1, 4, 6, 7, 9, 10] {
+            let (x, rest) = h.pop_min().unwrap();
+            assert_eq!(x, i);
+            h = rest;
+        }
+        assert!(h.pop_min().is_none());
+        h = h.insert(0);
+        assert_eq!(h.find_min(), &0);
+        assert_eq!(e.merge(&h).find_min(), &0);
+        assert_eq!(h.merge(&e).find_min(), &0);
+
+        check_heap_insert::<H>();
+        check_heap_merge::<H>();
+        check_heap_model::<H>();
+    }
+
+    // Compares the heap with BinaryHeap through inserts, deletions and merges with many duplicates
+    fn check_heap_model<H: Heap<i32>>() {
+        let mut h = H::empty();
+        let mut b = BinaryHeap::new();
+        for i in 0..1000 {
+            let x = i * 37 % 101;
+            h = h.insert(x);
+            b.push(Reverse(x));
+            if i % 3 == 0 {
+                assert_eq!(Some(h.find_min()), b.peek().map(|Reverse(x)| x));
+                h = h.delete_min();
+                b.pop();
+            }
+            if i % 50 == 0 {
+                let other = (0..i % 13).map(|j| j * 7 % 11).collect::<Vec<_>>();
+                h = h.merge(&other.iter().fold(H::empty(), |h, &x| h.insert(x)));
+                b.extend(other.into_iter().map(Reverse));
+            }
+        }
+        while let Some(Reverse(x)) = b.pop() {
+            assert_eq!(h.find_min(), &x);
+            h = h.delete_min();
+        }
+        assert!(h.is_empty());
+    }
+
+    // Unit tests of leftheap.rs and binheap.rs
+    fn check_heap_insert<H: Heap<i32>>() {
+        let mut h = H::empty().insert(4).insert(6).insert(10);
+        h = h.insert(1);
+        h = h.insert(7);
+        h = h.insert(12);
+        for i in &[1, 4, 6, 7, 10, 12] {
+            assert_eq!(h.find_min(), i);
+            h = h.delete_min();
+        }
+        assert!(h.is_empty());
+    }
+
+    fn check_heap_merge<H: Heap<i32>>() {
+        let h1 = H::empty().insert(3).insert(1).insert(7).insert(10);
+        let h2 = H::empty().insert(2).insert(4).insert(11).insert(0);
+        for mut h in [h1.merge(&h2), h2.merge(&h1)] {
+            for i in &[0, 1, 2, 3, 4, 7, 10, 11] {
+                assert_eq!(h.find_min(), i);
+                h = h.delete_min();
+            }
+            assert!(h.is_empty());
+        }
+    }
+
+    fn check_set<S: Set<i32>>() {
+        let s = S::empty().insert(5).insert(2).insert(4).insert(1).insert(6);
+        assert!(s.member(&4));
+        assert!(s.member(&1));
+        assert!(!s.member(&9));
+        let s = s.insert(9).insert(2);
+        assert!(s.member(&9));
+        assert!(s.member(&2));
+
+        check_set_member::<S>();
+        check_set_insert::<S>();
+    }
+
+    // Unit tests of bintree.rs and rbtree.rs
+    fn check_set_member<S: Set<i32>>() {
+        let s = [5, 2, 4, 1, 6, 7].iter().fold(S::empty(), |s, x| s.insert(*x));
+        assert!(s.member(&4));
+        assert!(s.member(&1));
+        assert!(s.member(&7));
+        assert!(!s.member(&9));
+    }
+
+    fn check_set_insert<S: Set<i32>>() {
+        let s = S::empty().insert(10).insert(3).insert(7).insert(1).insert(9);
+        let s = s.insert(10);
+        assert!(s.member(&10));
+        let s = s.insert(2);
+        assert!(s.member(&2));
+        assert!(!s.member(&5));
+    }
+
+    #[test]
+    fn test_stack() {
+        check_stack::<List<i32>>();
+        check_stack::<CatList<i32>>();
+        check_stack::<BinRandomAccessList<i32>>();
+        check_stack::<SkewBinRandomAccessList<i32>>();
+    }
+
+    #[test]
+    fn test_random_access_list() {
+        check_random_access_list::<BinRandomAccessList<i32>>();
+        check_random_access_list::<SkewBinRandomAccessList<i32>>();
+        check_random_access_list_model::<BinRandomAccessList<i32>>();
+        check_random_access_list_model::<SkewBinRandomAccessList<i32>>();
+    }
+
+    #[test]
+    fn test_queue() {
+        check_queue::<queue::Queue<i32>>();
+        check_queue::<BankersQueue<i32>>();
+        check_queue::<PhysicistsQueue<i32>>();
+        check_queue::<RealTimeQueue<i32>>();
+        check_queue::<HoodMelvilleQueue<i32>>();
+    }
+
+    #[test]
+    fn test_deque() {
+        check_deque::<deque::Deque<i32>>();
+        check_deque::<BankersDeque<i32>>();
+        check_deque::<RealTimeDeque<i32>>();
+        check_deque::<SimpleCatDeque<i32>>();
+        check_deque::<CatDeque<i32>>();
+    }
+
+    #[test]
+    fn test_deque_model() {
+        check_deque_model(deque::Deque::empty());
+        for c in 2..5 {
+            check_deque_model(BankersDeque::with_balance(c));
+        }
+        for c in 2..4 {
+            check_deque_model(RealTimeDeque::with_balance(c));
+        }
+        check_deque_model(SimpleCatDeque::empty());
+        check_deque_model(CatDeque::empty());
+    }
+
+    #[test]
+    fn test_heap() {
+        check_heap::<LeftHeap<i32>>();
+        check_heap::<BinHeap<i32>>();
+        check_heap::<BinHeap2<i32>>();
+        check_heap::<SkewBinHeap<i32>>();
+        check_heap::<BootstrappedHeap<i32>>();
+        check_heap::<BootstrappedHeap<i32, BinHeap<i32>>>();
+        check_heap::<SplayHeap<i32>>();
+        check_heap::<PairingHeap<i32>>();
+        check_heap::<LazyPairingHeap<i32>>();
+        check_heap::<WeightLeftHeap<i32>>();
+    }
+
+    #[test]
+    fn test_set() {
+        check_set::<BinTree<i32>>();
+        check_set::<RBTree<i32>>();
+    }
+}
@@ -0,0 +1,271 @@
+// Exercise 3.4
+//
+// Weight-biased leftist heap.
+// O(log n) insert, merge and delete_min, and O(1) find_min and len
+
+use std::fmt::Debug;
+use std::cmp::Ord;
+use std::mem;
+use ptr::Ptr;
+use traits;
+
+// The size of the left child is always greater than or equal to the size of the right child. Sizes
+// are cached in nodes instead of ranks, so the size of the whole heap is known for free.
+#[derive(Debug, Clone)]
+enum Node<T: Ord + Clone + Debug> {
+    Leaf,
+    Knot(usize, T, Link<T>, Link<T>),
+}
+
+type Link<T> = Ptr<Node<T>>;
+
+impl<T> Node<T>
+where T: Ord + Clone + Debug {
+    fn singleton(x: T) -> Link<T> {
+        Ptr::new(Node::Knot(1, x, Ptr::new(Node::Leaf), Ptr::new(Node::Leaf)))
+    }
+
+    fn size(&self) -> usize {
+        match *self {
+            Node::Leaf => 0,
+            Node::Knot(s, _, _, _) => s,
+        }
+    }
+
+    // Same as `LeftHeap`'s `make_knot` but it compares sizes. The child `b` is not built yet, so only
+    // its size is given and a placeholder is put in its place. Returns whether `b` is the left child.
+    fn make_knot(x: T, a: Link<T>, b_size: usize, placeholder: Link<T>) -> (Link<T>, bool) {
+        let size = a.size() + b_size + 1;
+        if a.size() >= b_size {
+            (Ptr::new(Node::Knot(size, x, a, placeholder)), false)
+        } else {
+            (Ptr::new(Node::Knot(size, x, placeholder, a)), true)
+        }
+    }
+
+    // Unlike rank, the size of a merged heap is known before merging. So which child the merged heap
+    // becomes is decided on the way down, and each node is built before its children in a single pass.
+    // The child to be filled is left as a hole and written through the node which was just allocated,
+    // so the path is not kept in recursion.
+    fn merge(h1: &Link<T>, h2: &Link<T>) -> Link<T> {
+        let leaf = Ptr::new(Node::Leaf);
+        let mut root = leaf.clone();
+        let (mut h1, mut h2) = (h1.clone(), h2.clone());
+        let mut hole = &mut root;
+        loop {
+            if let Node::Leaf = *h1 {
+                *hole = h2;
+                return root;
+            }
+            if let Node::Leaf = *h2 {
+                *hole = h1;
+                return root;
+            }
+            let (x, a, b, other) = match (&*h1, &*h2) {
+                (Node::Knot(_, x, a1, b1), Node::Knot(_, y, a2, b2)) => {
+                    if x <= y {
+                        (x.clone(), a1.clone(), b1.clone(), h2.clone())
+                    } else {
+                        (y.clone(), a2.clone(), b2.clone(), h1.clone())
+                    }
+                },
+                _ => unreachable!("leaves are handled above"),
+            };
+            let (knot, merged_is_left) = Node::make_knot(x, a, b.size() + other.size(), leaf.clone());
+            *hole = knot;
+            hole = match Ptr::get_mut(hole) {
+                Some(Node::Knot(_, _, l, r)) => if merged_is_left { l } else { r },
+                _ => unreachable!("the knot was just allocated"),
+            };
+            h1 = b;
+            h2 = other;
+        }
+    }
+}
+
+// Nodes of the heap are hidden
+#[derive(Debug, Clone)]
+pub struct WeightLeftHeap<T: Ord + Clone + Debug> {
+    root: Link<T>,
+}
+
+impl<T> WeightLeftHeap<T>
+where T: Ord + Clone + Debug {
+    pub fn empty() -> Self {
+        WeightLeftHeap{root: Ptr::new(Node::Leaf)}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.size() == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.root.size()
+    }
+
+    pub fn merge(&self, rhs: &Self) -> Self {
+        WeightLeftHeap{root: Node::merge(&self.root, &rhs.root)}
+    }
+
+    pub fn insert(&self, v: T) -> Self {
+        WeightLeftHeap{root: Node::merge(&Node::singleton(v), &self.root)}
+    }
+
+    pub fn pop_min(&self) -> Option<(&T, Self)> {
+        match *self.root {
+            Node::Leaf => None,
+            Node::Knot(_, ref x, ref a, ref b) => Some((x, WeightLeftHeap{root: Node::merge(a, b)})),
+        }
+    }
+
+    pub fn try_find_min(&self) -> Option<&T> {
+        match *self.root {
+            Node::Leaf => None,
+            Node::Knot(_, ref x, _, _) => Some(x),
+        }
+    }
+
+    pub fn try_delete_min(&self) -> Option<Self> {
+        self.pop_min().map(|(_, h)| h)
+    }
+
+    pub fn find_min(&self) -> &T {
+        self.try_find_min().expect("heap is empty!")
+    }
+
+    pub fn delete_min(&self) -> Self {
+        self.try_delete_min().expect("heap is empty!")
+    }
+
+    // Iterates elements in ascending order
+    pub fn iter(&self) -> traits::HeapIter<T, Self> {
+        traits::HeapIter::new(self.clone())
+    }
+
+    // Same as `LeftHeap::from_slice`. Makes a heap in O(n) by merging pairs of heaps repeatedly.
+    pub fn from_slice(a: &[T]) -> Self {
+        let mut heaps = a.iter()
+            .map(|e| Node::singleton(e.clone()))
+            .collect::<Vec<_>>();
+
+        while heaps.len() > 1 {
+            heaps = heaps.chunks(2).map(|pair| {
+                if pair.len() == 1 {
+                    pair[0].clone()
+                } else {
+                    Node::merge(&pair[0], &pair[1])
+                }
+            }).collect::<Vec<_>>();
+        }
+
+        match heaps.pop() {
+            Some(root) => WeightLeftHeap{root},
+            None => WeightLeftHeap::empty(),
+        }
+    }
+}
+
+// Only the right spine is O(log n). The left spine can be a long path (e.g. after inserting elements in
+// descending order) and dropping it recursively overflows the stack. Nodes owned only by this heap
+// are unlinked in a loop as `List` does.
+impl<T> Drop for WeightLeftHeap<T>
+where T: Ord + Clone + Debug {
+    fn drop(&mut self) {
+        let mut links = vec![mem::replace(&mut self.root, Ptr::new(Node::Leaf))];
+        while let Some(mut l) = links.pop() {
+            if let Some(n) = Ptr::get_mut(&mut l) {
+                if let Node::Knot(_, _, a, b) = mem::replace(n, Node::Leaf) {
+                    links.push(a);
+                    links.push(b);
+                }
+            }
+        }
+    }
+}
+
+impl<T> traits::Heap<T> for WeightLeftHeap<T>
+where T: Ord + Clone + Debug {
+    fn empty() -> Self {
+        WeightLeftHeap::empty()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn insert(&self, x: T) -> Self {
+        self.insert(x)
+    }
+
+    fn merge(&self, other: &Self) -> Self {
+        self.merge(other)
+    }
+
+    fn try_find_min(&self) -> Option<&T> {
+        self.try_find_min()
+    }
+
+    fn try_delete_min(&self) -> Option<Self> {
+        self.try_delete_min()
+    }
+
+    fn pop_min(&self) -> Option<(&T, Self)> {
+        self.pop_min()
+    }
+}
+
+impl<T> traits::HeapFamily for WeightLeftHeap<T>
+where T: Ord + Clone + Debug {
+    type Heap<E: Clone + Ord + Debug> = WeightLeftHeap<E>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Checks that the left child is not smaller than the right child and cached sizes are correct
+    fn check_weight(t: &Link<i32>) -> usize {
+        match **t {
+            Node::Leaf => 0,
+            Node::Knot(s, _, ref a, ref b) => {
+                let (sa, sb) = (check_weight(a), check_weight(b));
+                assert!(sa >= sb, "left: {}, right: {}", sa, sb);
+                assert_eq!(s, sa + sb + 1);
+                s
+            },
+        }
+    }
+
+    #[test]
+    fn test_weight() {
+        let h1 = WeightLeftHeap::from_slice(&[4, 6, 10, 6]);
+        let h2 = WeightLeftHeap::from_slice(&[1, 7, 9]);
+        let h = h1.merge(&h2);
+        assert_eq!(h.len(), 7);
+        check_weight(&h.root);
+        assert_eq!(h.iter().collect::<Vec<_>>(), vec![1, 4, 6, 6, 7, 9, 10]);
+
+        let mut h = WeightLeftHeap::empty();
+        for i in 0..1000 {
+            h = h.insert(i * 37 % 101);
+            if i % 3 == 0 {
+                h = h.delete_min();
+            }
+            if i % 50 == 0 {
+                h = h.merge(&WeightLeftHeap::from_slice(&(0..i % 13).collect::<Vec<_>>()));
+            }
+        }
+        check_weight(&h.root);
+    }
+
+    #[test]
+    fn test_long() {
+        // Descending insertion makes a long left spine
+        let n = 100000;
+        let h = (0..n).rev().fold(WeightLeftHeap::empty(), |h, i| h.insert(i));
+        assert_eq!(h.len(), n as usize);
+        let h = h.merge(&(0..n).fold(WeightLeftHeap::empty(), |h, i| h.insert(i)));
+        assert_eq!(h.len(), 2 * n as usize);
+        assert!(h.iter().eq((0..n).flat_map(|i| vec![i, i])));
+    }
+}